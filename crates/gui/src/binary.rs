@@ -0,0 +1,254 @@
+//! Renders the raw bytes of logs created by `Logger::log_bytes`, as a hex dump
+//! or decoded as UTF-8, CBOR or protobuf.
+
+use std::fmt::{self, Write};
+
+use ciborium::value::Value;
+
+const BYTES_PER_LINE: usize = 16;
+// Deeper messages are shown as bytes, as protobuf decoding is only a guess
+// without the schema.
+const MAX_PROTOBUF_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Decoding {
+	#[default]
+	Hex,
+	Utf8,
+	Cbor,
+	Protobuf,
+}
+
+impl Decoding {
+	pub const ALL: [Self; 4] = [Self::Hex, Self::Utf8, Self::Cbor, Self::Protobuf];
+
+	/// How bytes of `content_type` are first shown.
+	pub fn for_content_type(content_type: &str) -> Self {
+		match content_type.split(';').next().unwrap_or_default().trim() {
+			"application/cbor" => Self::Cbor,
+			"application/protobuf" | "application/x-protobuf" => Self::Protobuf,
+			content_type if content_type.starts_with("text/") || content_type == "application/json" =>
+				Self::Utf8,
+			_ => Self::Hex,
+		}
+	}
+
+	/// Renders `data`, or returns why it can't be decoded this way.
+	pub fn render(self, data: &[u8]) -> Result<String, String> {
+		match self {
+			Self::Hex => Ok(hex_dump(data)),
+			Self::Utf8 => String::from_utf8(data.to_vec()).map_err(|error| error.to_string()),
+			Self::Cbor => {
+				let mut reader = data;
+				let value: Value =
+					ciborium::de::from_reader(&mut reader).map_err(|error| error.to_string())?;
+
+				if !reader.is_empty() {
+					return Err(format!("{} bytes follow the CBOR value", reader.len()));
+				}
+
+				let mut out = String::new();
+				write_cbor(&mut out, &value, 0).map_err(|error| error.to_string())?;
+
+				Ok(out)
+			},
+			Self::Protobuf => {
+				let mut out = String::new();
+				write_protobuf(&mut out, data, 0)?;
+
+				Ok(out)
+			},
+		}
+	}
+}
+
+impl fmt::Display for Decoding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Hex => "Hex",
+			Self::Utf8 => "UTF-8",
+			Self::Cbor => "CBOR",
+			Self::Protobuf => "Protobuf",
+		})
+	}
+}
+
+// The same format as `hexdump -C`, which is also used for the message of the
+// log.
+fn hex_dump(data: &[u8]) -> String {
+	let mut out = String::new();
+
+	for (index, line) in data.chunks(BYTES_PER_LINE).enumerate() {
+		let _ = write!(out, "{:08x} ", index * BYTES_PER_LINE);
+
+		for column in 0..BYTES_PER_LINE {
+			if column % 8 == 0 {
+				out.push(' ');
+			}
+
+			match line.get(column) {
+				Some(byte) => {
+					let _ = write!(out, "{byte:02x} ");
+				},
+				None => out.push_str("   "),
+			}
+		}
+
+		let _ = writeln!(out, " |{}|", ascii(line));
+	}
+
+	let _ = write!(out, "{:08x}", data.len());
+
+	out
+}
+
+fn ascii(bytes: &[u8]) -> String {
+	bytes
+		.iter()
+		.map(|byte| {
+			if byte.is_ascii_graphic() || *byte == b' ' {
+				char::from(*byte)
+			} else {
+				'.'
+			}
+		})
+		.collect()
+}
+
+// Writes `value` in CBOR diagnostic notation, with one item per line.
+fn write_cbor(out: &mut String, value: &Value, depth: usize) -> fmt::Result {
+	let indent = "  ".repeat(depth + 1);
+	let closing = "  ".repeat(depth);
+
+	match value {
+		Value::Integer(integer) => write!(out, "{}", i128::from(*integer)),
+		Value::Bytes(bytes) => {
+			out.push_str("h'");
+
+			for byte in bytes {
+				write!(out, "{byte:02x}")?;
+			}
+
+			out.push('\'');
+			Ok(())
+		},
+		Value::Float(float) => write!(out, "{float:?}"),
+		Value::Text(text) => write!(out, "{text:?}"),
+		Value::Bool(boolean) => write!(out, "{boolean}"),
+		Value::Null => out.write_str("null"),
+		Value::Tag(tag, value) => {
+			write!(out, "{tag}(")?;
+			write_cbor(out, value, depth)?;
+			out.write_str(")")
+		},
+		Value::Array(items) if items.is_empty() => out.write_str("[]"),
+		Value::Array(items) => {
+			out.push_str("[\n");
+
+			for item in items {
+				out.push_str(&indent);
+				write_cbor(out, item, depth + 1)?;
+				out.push_str(",\n");
+			}
+
+			write!(out, "{closing}]")
+		},
+		Value::Map(entries) if entries.is_empty() => out.write_str("{}"),
+		Value::Map(entries) => {
+			out.push_str("{\n");
+
+			for (key, value) in entries {
+				out.push_str(&indent);
+				write_cbor(out, key, depth + 1)?;
+				out.push_str(": ");
+				write_cbor(out, value, depth + 1)?;
+				out.push_str(",\n");
+			}
+
+			write!(out, "{closing}}}")
+		},
+		_ => write!(out, "{value:?}"),
+	}
+}
+
+// Decodes `data` as a protobuf message without its schema, writing each field
+// as its number and wire type. Length-delimited fields are shown as a nested
+// message if they decode as one, otherwise as a string or bytes.
+fn write_protobuf(out: &mut String, mut data: &[u8], depth: usize) -> Result<(), String> {
+	let indent = "  ".repeat(depth);
+
+	while !data.is_empty() {
+		let key = read_varint(&mut data)?;
+		let field = key >> 3;
+
+		if field == 0 {
+			return Err("Invalid field number 0".to_string());
+		}
+
+		let _ = write!(out, "{indent}{field}: ");
+
+		match key & 0b111 {
+			0 => {
+				let _ = writeln!(out, "{}", read_varint(&mut data)?);
+			},
+			1 => {
+				let bytes = take(&mut data, 8)?;
+				let value = u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+				let _ = writeln!(out, "{value} (fixed64, {})", f64::from_bits(value));
+			},
+			2 => {
+				let length = usize::try_from(read_varint(&mut data)?).map_err(|error| error.to_string())?;
+				let bytes = take(&mut data, length)?;
+				let mut nested = String::new();
+
+				if depth < MAX_PROTOBUF_DEPTH
+					&& !bytes.is_empty()
+					&& write_protobuf(&mut nested, bytes, depth + 1).is_ok()
+				{
+					let _ = write!(out, "{{\n{nested}{indent}}}\n");
+				} else if let Ok(text) = std::str::from_utf8(bytes) {
+					let _ = writeln!(out, "{text:?}");
+				} else {
+					let _ = writeln!(out, "{} bytes |{}|", bytes.len(), ascii(bytes));
+				}
+			},
+			5 => {
+				let bytes = take(&mut data, 4)?;
+				let value = u32::from_le_bytes(bytes.try_into().unwrap_or_default());
+				let _ = writeln!(out, "{value} (fixed32, {})", f32::from_bits(value));
+			},
+			wire_type =>
+				return Err(format!(
+					"Unsupported wire type {wire_type} for field {field}"
+				)),
+		}
+	}
+
+	Ok(())
+}
+
+fn read_varint(data: &mut &[u8]) -> Result<u64, String> {
+	let mut value = 0_u64;
+
+	for (index, byte) in data.iter().enumerate().take(10) {
+		value |= u64::from(byte & 0x7f) << (7 * index);
+
+		if byte & 0x80 == 0 {
+			*data = &data[index + 1..];
+			return Ok(value);
+		}
+	}
+
+	Err("Invalid varint".to_string())
+}
+
+fn take<'a>(data: &mut &'a [u8], length: usize) -> Result<&'a [u8], String> {
+	if data.len() < length {
+		return Err("Field is longer than the remaining data".to_string());
+	}
+
+	let (taken, rest) = data.split_at(length);
+	*data = rest;
+
+	Ok(taken)
+}
@@ -17,6 +17,7 @@ pub use iced;
 use iced::{
 	executor, subscription,
 	theme::Custom,
+	time,
 	widget::{button, checkbox, column, container, row, text, text_input, Rule},
 	window::close,
 	Alignment, Application, Command, Element, Length, Subscription, Theme as IcedTheme,
@@ -35,13 +36,18 @@ use tonic::{transport::Channel, Response, Status, Streaming};
 
 use codectrl_protobuf_bindings::{
 	data::Log,
+	extensions::{
+		extended_log_server_client::ExtendedLogServerClient, CallSiteRule, CallSiteStatus,
+		EvictedLogs, LogId, LogMetadata, LogPin, TestCase,
+	},
 	logs_service::{log_server_client::LogServerClient, Connection, RequestStatus, ServerDetails},
 };
 use codectrl_server::{self, ServerResult};
 use theming::{Theme, ThemeEngine};
 
-use crate::view::View;
+use crate::{binary::Decoding, view::View};
 
+mod binary;
 mod styles;
 mod theming;
 mod view;
@@ -59,6 +65,13 @@ pub enum ThemeState {
 	Ended,
 }
 
+// Watches for logs evicted by the server, which are then removed from the
+// list of logs.
+pub enum EvictionState {
+	NotConnected(String),
+	Watching(String, Streaming<EvictedLogs>),
+}
+
 type Client = LogServerClient<Channel>;
 pub enum GrpcConnection {
 	NotConnected(String, u32),
@@ -78,6 +91,18 @@ pub enum Message {
 	// main view
 	LogAppearanceStateChanged,
 	LogClicked(Log),
+	LogMetadataReceived(String, Option<LogMetadata>),
+	LogIdClicked(String),
+	ResumeBreakpoint(String),
+	BreakpointResumed(String),
+	PinLog(String, bool),
+	LogPinned(String, bool),
+	LogsEvicted(Vec<String>),
+	BinaryDecodingSelected(Decoding),
+	ShowEnvironment(bool),
+	ToggleTestGrouping(bool),
+	RefreshTestCases,
+	TestCasesReceived(Option<Vec<TestCase>>),
 	LogIndexChanged(Option<Cow<'static, str>>),
 	LogDetailsSplitResize(u16),
 	LogDetailsInnerSplitResize(u16),
@@ -90,6 +115,11 @@ pub enum Message {
 	FilterCaseSensitivityChanged(bool),
 	FilterRegexChanged(bool),
 
+	// log points view
+	RefreshCallSites,
+	CallSitesReceived(Option<Vec<CallSiteStatus>>),
+	SetCallSiteRule(CallSiteRule),
+
 	// general
 	UpdateViewState(ViewState),
 	SplitResize(u16),
@@ -118,6 +148,7 @@ pub enum ViewState {
 	Searching,
 	#[default]
 	Main,
+	LogPoints,
 }
 
 fn separator<'a, Message>() -> iced_aw::menu::menu_tree::MenuTree<'a, Message, iced::Renderer> {
@@ -161,6 +192,7 @@ pub struct App {
 	view_state: ViewState,
 	main_view: views::Main,
 	searching_view: views::Searching,
+	log_points_view: views::LogPoints,
 
 	// themes
 	theme: Theme,
@@ -184,6 +216,7 @@ impl App {
 			view_state: ViewState::default(),
 			main_view: views::Main::default(),
 			searching_view: views::Searching::default(),
+			log_points_view: views::LogPoints::default(),
 			theme: Theme::catppuccin_frappe_sky(),
 			theme_engine: ThemeEngine::new(),
 		}
@@ -191,6 +224,120 @@ impl App {
 
 	fn send_message(message: Message) -> Command<Message> { Command::perform(async {}, |()| message) }
 
+	fn fetch_log_metadata(&self, uuid: String) -> Command<Message> {
+		let address = format!("http://{}:{}", self.host, self.port);
+
+		Command::perform(
+			{
+				let uuid = uuid.clone();
+
+				async move {
+					let mut client = ExtendedLogServerClient::connect(address).await.ok()?;
+
+					client
+						.get_log_metadata(LogId { uuid })
+						.await
+						.ok()
+						.map(Response::into_inner)
+				}
+			},
+			move |metadata| Message::LogMetadataReceived(uuid, metadata),
+		)
+	}
+
+	fn fetch_test_cases(&self) -> Command<Message> {
+		let address = format!("http://{}:{}", self.host, self.port);
+
+		Command::perform(
+			async move {
+				let mut client = ExtendedLogServerClient::connect(address).await.ok()?;
+
+				client
+					.get_test_cases(())
+					.await
+					.ok()
+					.map(|response| response.into_inner().test_cases)
+			},
+			Message::TestCasesReceived,
+		)
+	}
+
+	fn resume_breakpoint(&self, uuid: String) -> Command<Message> {
+		let address = format!("http://{}:{}", self.host, self.port);
+
+		Command::perform(
+			{
+				let uuid = uuid.clone();
+
+				async move {
+					let mut client = ExtendedLogServerClient::connect(address).await?;
+					client.resume_breakpoint(LogId { uuid }).await?;
+
+					Ok::<_, Error>(())
+				}
+			},
+			move |result| match result {
+				Ok(()) => Message::BreakpointResumed(uuid),
+				Err(error) => Message::AddServerError(Some(Arc::new(error))),
+			},
+		)
+	}
+
+	fn pin_log(&self, uuid: String, pinned: bool) -> Command<Message> {
+		let address = format!("http://{}:{}", self.host, self.port);
+
+		Command::perform(
+			{
+				let uuid = uuid.clone();
+
+				async move {
+					let mut client = ExtendedLogServerClient::connect(address).await?;
+					client.set_log_pinned(LogPin { uuid, pinned }).await?;
+
+					Ok::<_, Error>(())
+				}
+			},
+			move |result| match result {
+				Ok(()) => Message::LogPinned(uuid, pinned),
+				Err(error) => Message::AddServerError(Some(Arc::new(error))),
+			},
+		)
+	}
+
+	fn fetch_call_sites(&self) -> Command<Message> {
+		let address = format!("http://{}:{}", self.host, self.port);
+
+		Command::perform(
+			async move {
+				let mut client = ExtendedLogServerClient::connect(address).await.ok()?;
+
+				client
+					.get_call_sites(())
+					.await
+					.ok()
+					.map(|response| response.into_inner().call_sites)
+			},
+			Message::CallSitesReceived,
+		)
+	}
+
+	fn set_call_site_rule(&self, rule: CallSiteRule) -> Command<Message> {
+		let address = format!("http://{}:{}", self.host, self.port);
+
+		Command::perform(
+			async move {
+				let mut client = ExtendedLogServerClient::connect(address).await?;
+				client.set_call_site_rule(rule).await?;
+
+				Ok::<_, Error>(())
+			},
+			|result| match result {
+				Ok(()) => Message::RefreshCallSites,
+				Err(error) => Message::AddServerError(Some(Arc::new(error))),
+			},
+		)
+	}
+
 	fn start_refresh_errors_subscription() -> Subscription<Message> {
 		subscription::unfold(
 			"RefreshErrors",
@@ -227,6 +374,41 @@ impl App {
 		)
 	}
 
+	fn start_evicted_logs_subscription(&self) -> Subscription<Message> {
+		subscription::unfold(
+			"EvictedLogs",
+			EvictionState::NotConnected(format!("http://{}:{}", self.host, self.port)),
+			move |state| async move {
+				match state {
+					EvictionState::NotConnected(address) => {
+						let stream = async {
+							let mut client = ExtendedLogServerClient::connect(address.clone()).await?;
+							let stream = client.watch_evicted_logs(()).await?.into_inner();
+
+							Ok::<_, Error>(stream)
+						};
+
+						match stream.await {
+							Ok(stream) => (Message::NoOp, EvictionState::Watching(address, stream)),
+							Err(_) => {
+								tokio::time::sleep(Duration::new(1, 0)).await;
+
+								(Message::NoOp, EvictionState::NotConnected(address))
+							},
+						}
+					},
+					EvictionState::Watching(address, mut stream) => match stream.message().await {
+						Ok(Some(evicted)) => (
+							Message::LogsEvicted(evicted.uuids),
+							EvictionState::Watching(address, stream),
+						),
+						_ => (Message::NoOp, EvictionState::NotConnected(address)),
+					},
+				}
+			},
+		)
+	}
+
 	fn start_get_logs_subscription(&self) -> Subscription<Message> {
 		subscription::unfold(
 			"GetLogs",
@@ -371,9 +553,21 @@ impl Application for App {
 		use Message::*;
 
 		match message {
+			LogClicked(ref log) => {
+				let fetch = self.fetch_log_metadata(log.uuid.clone());
+
+				Command::batch(vec![self.main_view.update(message), fetch])
+			},
 			LogAppearanceStateChanged
 			| ServerAddLog(_)
-			| LogClicked(_)
+			| LogMetadataReceived(..)
+			| LogIdClicked(_)
+			| BreakpointResumed(_)
+			| LogPinned(..)
+			| LogsEvicted(_)
+			| BinaryDecodingSelected(_)
+			| ShowEnvironment(_)
+			| TestCasesReceived(_)
 			| LogDetailsSplitResize(_)
 			| LogDetailsInnerSplitResize(_)
 			| LogIndexChanged(_)
@@ -381,14 +575,37 @@ impl Application for App {
 			| SortLogs
 			| LogDetailsSplitClose => self.main_view.update(message),
 
+			ToggleTestGrouping(group_by_test) => {
+				let fetch = if group_by_test {
+					self.fetch_test_cases()
+				} else {
+					Command::none()
+				};
+
+				Command::batch(vec![self.main_view.update(message), fetch])
+			},
+			RefreshTestCases => self.fetch_test_cases(),
+			ResumeBreakpoint(uuid) => self.resume_breakpoint(uuid),
+			PinLog(uuid, pinned) => self.pin_log(uuid, pinned),
+
 			FilterTextChanged(_)
 			| ClearFilterText
 			| FilterCaseSensitivityChanged(_)
 			| FilterRegexChanged(_) => self.searching_view.update(message),
 
+			RefreshCallSites => self.fetch_call_sites(),
+			CallSitesReceived(_) => self.log_points_view.update(message),
+			SetCallSiteRule(rule) => self.set_call_site_rule(rule),
+
 			UpdateViewState(state) => {
+				let fetch = if state == ViewState::LogPoints {
+					self.fetch_call_sites()
+				} else {
+					Command::none()
+				};
+
 				self.view_state = state;
-				Command::none()
+				fetch
 			},
 			SplitResize(size) => {
 				self.split_size = Some(size);
@@ -562,6 +779,12 @@ impl Application for App {
 					button(text(&self.main_view.log_appearance)).on_press(Message::LogAppearanceStateChanged)
 				]
 				.align_items(Alignment::Center),
+				checkbox(
+					"Group by test",
+					self.main_view.group_by_test,
+					Message::ToggleTestGrouping
+				),
+				button("Log points").on_press(Message::UpdateViewState(ViewState::LogPoints)),
 				Rule::horizontal(1.0),
 				text(format!("Server address: {}:{}", self.host, self.port)),
 				text(format!("Server uptime: {}s", self.uptime.as_secs())),
@@ -582,6 +805,7 @@ impl Application for App {
 					container(match self.view_state {
 						ViewState::Main => self.main_view.view(),
 						ViewState::Searching => self.searching_view.view(),
+						ViewState::LogPoints => self.log_points_view.view(),
 					})
 					.width(Length::Fill),
 					self.split_size,
@@ -604,10 +828,25 @@ impl Application for App {
 	}
 
 	fn subscription(&self) -> Subscription<Self::Message> {
-		Subscription::batch(vec![
+		let mut subscriptions = vec![
 			Self::start_refresh_errors_subscription(),
 			self.start_load_themes_subscription(),
 			self.start_get_logs_subscription(),
-		])
+			self.start_evicted_logs_subscription(),
+		];
+
+		// Tests report their results after their last log, so the test cases have
+		// to be polled rather than fetched whenever a log is received.
+		if self.main_view.group_by_test {
+			subscriptions.push(time::every(Duration::from_secs(1)).map(|_| Message::RefreshTestCases));
+		}
+
+		// Call sites are registered as they first log, so they are polled while
+		// they are shown.
+		if self.view_state == ViewState::LogPoints {
+			subscriptions.push(time::every(Duration::from_secs(2)).map(|_| Message::RefreshCallSites));
+		}
+
+		Subscription::batch(subscriptions)
 	}
 }
@@ -0,0 +1,110 @@
+use iced::{
+	widget::{button, checkbox, column, container, row, scrollable, text},
+	Alignment, Command, Element, Length,
+};
+
+use codectrl_protobuf_bindings::extensions::{CallSiteRule, CallSiteStatus};
+
+use crate::{view::View, Message, ViewState};
+
+#[derive(Debug, Clone, Default)]
+pub struct LogPoints {
+	call_sites: Vec<CallSiteStatus>,
+}
+
+impl View for LogPoints {
+	type Message = Message;
+
+	fn title(&self) -> String { String::from("Log points") }
+
+	fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+		use Message::*;
+
+		match message {
+			CallSitesReceived(Some(call_sites)) => {
+				self.call_sites = call_sites;
+				Command::none()
+			},
+			_ => Command::none(),
+		}
+	}
+
+	fn view(&self) -> Element<'_, Self::Message> {
+		let mut call_sites = column![].spacing(4.0);
+
+		for status in &self.call_sites {
+			let Some(call_site) = &status.call_site else {
+				continue;
+			};
+
+			// Call sites without a rule send every log.
+			let rule = status.rule.clone().unwrap_or_else(|| CallSiteRule {
+				call_site_id: call_site.id.clone(),
+				enabled: true,
+				..CallSiteRule::default()
+			});
+
+			let mut sampling = vec![];
+
+			if let Some(rate_limit) = rule.rate_limit {
+				sampling.push(format!("at most {rate_limit} per second"));
+			}
+
+			if let Some(one_in) = rule.one_in {
+				sampling.push(format!("one in {one_in}"));
+			}
+
+			let toggle = checkbox("", rule.enabled, {
+				let rule = rule.clone();
+
+				move |enabled| {
+					Message::SetCallSiteRule(CallSiteRule {
+						enabled,
+						..rule.clone()
+					})
+				}
+			});
+
+			call_sites = call_sites.push(
+				row![
+					toggle,
+					column![
+						text(&call_site.function),
+						text(format!(
+							"{}:{}:{}",
+							call_site.file_path, call_site.line_number, call_site.column_number
+						))
+						.size(14),
+						text(sampling.join(", ")).size(14),
+					]
+					.width(Length::Fill),
+				]
+				.align_items(Alignment::Center),
+			);
+		}
+
+		let content: Element<'_, _> = if self.call_sites.is_empty() {
+			text(
+				"No call sites have been registered. Log points are enabled by running the program with \
+				 CODECTRL_LOG_POINTS set.",
+			)
+			.into()
+		} else {
+			scrollable(call_sites).width(Length::Fill).into()
+		};
+
+		container(
+			column![
+				row![
+					text(self.title()).width(Length::Fill),
+					button("Back to logs").on_press(Message::UpdateViewState(ViewState::Main)),
+				]
+				.align_items(Alignment::Center),
+				content,
+			]
+			.spacing(10.0),
+		)
+		.padding(10.0)
+		.into()
+	}
+}
@@ -6,13 +6,17 @@ use crate::{
 	Message,
 };
 use chrono::Local;
+use codectrl_protobuf_bindings::extensions::{TestCase, TestOutcome};
 
 use iced::{
-	widget::{column, container, scrollable, text},
+	widget::{button, column, container, scrollable, text},
 	Command, Element, Length,
 };
 use iced_aw::{split::Axis, Split};
-use std::fmt;
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+};
 
 use self::{log_details_view::LogDetails, log_item::LogItem};
 
@@ -47,7 +51,11 @@ impl fmt::Display for LogAppearanceState {
 #[derive(Debug, Clone, Default)]
 pub struct Main {
 	pub log_appearance: LogAppearanceState,
+	pub group_by_test: bool,
 	logs: Vec<LogItem>,
+	test_cases: Vec<TestCase>,
+	// The IDs of the logs that are pinned, which the server never evicts.
+	pinned: HashSet<String>,
 
 	log_details_view: Option<LogDetails>,
 	log_details_split: u16,
@@ -81,8 +89,9 @@ impl View for Main {
 			},
 			LogClicked(log) => {
 				let uuid = log.uuid.clone();
+				let pinned = self.pinned.contains(&uuid);
 
-				self.log_details_view = Some(LogDetails::new(log));
+				self.log_details_view = Some(LogDetails::new(log, pinned));
 				self.send_message(UpdateLogItems(Box::new(LogIndexChanged(Some(uuid.into())))))
 			},
 			LogDetailsSplitResize(size) => {
@@ -95,6 +104,70 @@ impl View for Main {
 				} else {
 					Command::none()
 				},
+			LogMetadataReceived(uuid, metadata) => {
+				if metadata.as_ref().is_some_and(|metadata| metadata.pinned) {
+					self.pinned.insert(uuid.clone());
+				}
+
+				if let (Some(log_details_view), Some(metadata)) = (self.log_details_view.as_mut(), metadata)
+				{
+					if log_details_view.uuid() == uuid {
+						log_details_view.set_metadata(metadata);
+					}
+				}
+
+				Command::none()
+			},
+			LogPinned(ref uuid, pinned) => {
+				if pinned {
+					self.pinned.insert(uuid.clone());
+				} else {
+					self.pinned.remove(uuid);
+				}
+
+				if let Some(ref mut log_details_view) = self.log_details_view {
+					log_details_view.update(message)
+				} else {
+					Command::none()
+				}
+			},
+			LogsEvicted(uuids) => {
+				let uuids = uuids.into_iter().collect::<HashSet<_>>();
+
+				self
+					.logs
+					.retain(|log_item| !uuids.contains(&log_item.log().uuid));
+				self.pinned.retain(|uuid| !uuids.contains(uuid));
+
+				Command::none()
+			},
+			BreakpointResumed(_) | BinaryDecodingSelected(_) | ShowEnvironment(_) =>
+				if let Some(ref mut log_details_view) = self.log_details_view {
+					log_details_view.update(message)
+				} else {
+					Command::none()
+				},
+			LogIdClicked(uuid) => self
+				.logs
+				.iter()
+				.find(|log_item| log_item.log().uuid == uuid)
+				.map_or_else(Command::none, |log_item| {
+					self.send_message(LogClicked(log_item.log().clone()))
+				}),
+			ToggleTestGrouping(group_by_test) => {
+				self.group_by_test = group_by_test;
+				Command::none()
+			},
+			TestCasesReceived(test_cases) => {
+				if let Some(mut test_cases) = test_cases {
+					// Failed tests are listed first, so their logs are found straight away.
+					test_cases.sort_by_key(|test_case| test_case.outcome != Some(TestOutcome::Failed.into()));
+
+					self.test_cases = test_cases;
+				}
+
+				Command::none()
+			},
 			LogDetailsSplitClose => {
 				self.log_details_view = None;
 				self.send_message(UpdateLogItems(Box::new(LogIndexChanged(None))))
@@ -116,11 +189,11 @@ impl View for Main {
 	}
 
 	fn view(&self) -> iced::Element<'_, Self::Message> {
-		let mut elements = vec![];
-
-		for item in &self.logs {
-			elements.push(item.view());
-		}
+		let elements = if self.group_by_test {
+			self.grouped_log_items()
+		} else {
+			self.logs.iter().map(LogItem::view).collect()
+		};
 
 		let logs = scrollable(column(elements).spacing(0.5).padding(10.0)).width(Length::Fill);
 
@@ -146,3 +219,59 @@ impl View for Main {
 		container(column![text("Main view"), view].width(Length::Fill)).into()
 	}
 }
+
+impl Main {
+	// Lists the logs under a heading for the test that created them, followed by
+	// the logs that weren't created by a test.
+	fn grouped_log_items(&self) -> Vec<Element<'_, Message>> {
+		let mut elements = vec![];
+		let mut remaining: HashMap<&str, &LogItem> = self
+			.logs
+			.iter()
+			.map(|log_item| (log_item.log().uuid.as_str(), log_item))
+			.collect();
+
+		for test_case in &self.test_cases {
+			let result = match test_case.outcome.and_then(TestOutcome::from_i32) {
+				Some(TestOutcome::Passed) => "PASS",
+				Some(TestOutcome::Failed) => "FAIL",
+				// Still running, or detected without the `test` attribute.
+				None => "NO RESULT",
+			};
+			let heading = text(format!(
+				"{result} {} ({} logs)",
+				test_case.name,
+				test_case.log_ids.len()
+			));
+
+			// Selecting a test opens its first log.
+			elements.push(match test_case.log_ids.first() {
+				Some(uuid) => button(heading)
+					.on_press(Message::LogIdClicked(uuid.clone()))
+					.width(Length::Fill)
+					.into(),
+				None => heading.into(),
+			});
+
+			for uuid in &test_case.log_ids {
+				if let Some(log_item) = remaining.remove(uuid.as_str()) {
+					elements.push(log_item.view());
+				}
+			}
+		}
+
+		let ungrouped = self
+			.logs
+			.iter()
+			.filter(|log_item| remaining.contains_key(log_item.log().uuid.as_str()))
+			.map(LogItem::view)
+			.collect::<Vec<_>>();
+
+		if !ungrouped.is_empty() {
+			elements.push(text("Not in a test").into());
+			elements.extend(ungrouped);
+		}
+
+		elements
+	}
+}
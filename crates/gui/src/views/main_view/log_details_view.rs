@@ -1,34 +1,295 @@
-use crate::{view::View, Message};
+use crate::{binary::Decoding, view::View, Message};
 
-use codectrl_protobuf_bindings::data::Log;
+use codectrl_protobuf_bindings::{
+	data::Log,
+	extensions::{CallEvent, LogMetadata},
+};
 use iced::{
-	widget::{button, column, container, row, text},
-	Command,
+	widget::{button, checkbox, column, container, pick_list, row, scrollable, text},
+	Command, Font,
 };
 use iced_aw::{split::Axis, Split};
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, TextDiff};
+use std::{fs, time::Duration};
 
 #[derive(Debug, Clone, Default)]
 pub struct LogDetails {
 	log: Log,
+	metadata: Option<LogMetadata>,
+	source_diff: Option<Vec<String>>,
 	split_size: Option<u16>,
+	breakpoint_resumed: bool,
+	decoding: Decoding,
+	show_environment: bool,
+	pinned: bool,
 }
 
 impl LogDetails {
-	pub fn new(log: Log) -> Self {
+	pub fn new(log: Log, pinned: bool) -> Self {
 		Self {
 			log,
+			metadata: None,
+			source_diff: None,
 			split_size: None,
+			breakpoint_resumed: false,
+			decoding: Decoding::default(),
+			show_environment: false,
+			pinned,
+		}
+	}
+
+	pub fn uuid(&self) -> &str { &self.log.uuid }
+
+	pub fn set_metadata(&mut self, metadata: LogMetadata) {
+		self.source_diff = self.diff_current_source(&metadata);
+
+		if let Some(binary) = &metadata.binary {
+			self.decoding = Decoding::for_content_type(&binary.content_type);
+		}
+
+		self.pinned = metadata.pinned;
+		self.metadata = Some(metadata);
+	}
+
+	// Re-reads the logged file and, if it has changed since the log was
+	// created, diffs the snippet against the same lines of the current file.
+	fn diff_current_source(&self, metadata: &LogMetadata) -> Option<Vec<String>> {
+		let captured_hash = metadata.file_hashes.get(&self.log.file_name)?;
+		let current = fs::read(&self.log.file_name).ok()?;
+
+		if format!("{:x}", Sha256::digest(&current)) == *captured_hash {
+			return None;
+		}
+
+		let first = *self.log.code_snippet.keys().next()? as usize;
+		let last = *self.log.code_snippet.keys().last()? as usize;
+
+		let captured = self
+			.log
+			.code_snippet
+			.values()
+			.map(|line| format!("{line}\n"))
+			.collect::<String>();
+		let current = String::from_utf8_lossy(&current)
+			.lines()
+			.skip(first.saturating_sub(1))
+			.take(last.saturating_sub(first) + 1)
+			.map(|line| format!("{line}\n"))
+			.collect::<String>();
+
+		let diff = TextDiff::from_lines(&captured, &current)
+			.iter_all_changes()
+			.map(|change| {
+				let sign = match change.tag() {
+					ChangeTag::Delete => "-",
+					ChangeTag::Insert => "+",
+					ChangeTag::Equal => " ",
+				};
+
+				format!("{sign} {}", change.value().trim_end_matches('\n'))
+			})
+			.collect();
+
+		Some(diff)
+	}
+
+	// The snippet is read from disk when the log is created, so it only
+	// reflects the code that produced the log if the binary was built from the
+	// same, unmodified commit.
+	fn snippet_warning(&self) -> Option<String> {
+		let git = self.metadata.as_ref()?.git.as_ref()?;
+		let short = |commit: &str| commit.chars().take(7).collect::<String>();
+
+		match git.build_commit.as_deref() {
+			Some(build_commit) if build_commit != git.commit => Some(format!(
+				"Built from commit {} but the source is at commit {} on {}: the snippet may not match the \
+				 code that produced this log",
+				short(build_commit),
+				short(&git.commit),
+				git.branch
+			)),
+			_ if git.dirty || git.build_dirty == Some(true) => Some(format!(
+				"The repository had uncommitted changes: the snippet may not match commit {}",
+				short(&git.commit)
+			)),
+			_ => None,
 		}
 	}
 
 	fn trace_view(&self) -> iced::Element<'_, Message> {
-		let container = container(text(&self.log.message));
+		let uuid = self.log.uuid.clone();
+
+		// Pinned logs are kept by the server when it evicts old logs.
+		let mut trace = column![checkbox("Pinned", self.pinned, move |pinned| {
+			Message::PinLog(uuid.clone(), pinned)
+		})];
+
+		// Logs from an instrumented function link to the other log of the same
+		// call.
+		if let Some(call) = self
+			.metadata
+			.as_ref()
+			.and_then(|metadata| metadata.call.as_ref())
+		{
+			let (description, pair) = match CallEvent::from_i32(call.event) {
+				Some(CallEvent::Exit) => (
+					format!(
+						"Exit from {} after {:?} (call {})",
+						call.function,
+						Duration::from_nanos(call.elapsed_nanos.unwrap_or_default()),
+						call.call_id
+					),
+					"Go to entry",
+				),
+				_ => (
+					format!("Entry to {} (call {})", call.function, call.call_id),
+					"Go to exit",
+				),
+			};
+
+			trace = trace.push(row![
+				text(description),
+				button(pair).on_press(Message::LogIdClicked(call.paired_log_id.clone()))
+			]);
+		}
+
+		// The program that sent a breakpoint log waits for it to be resumed.
+		if let Some(breakpoint) = self
+			.metadata
+			.as_ref()
+			.and_then(|metadata| metadata.breakpoint.as_ref())
+		{
+			trace = trace.push(
+				if self.breakpoint_resumed {
+					row![text("Resumed")]
+				} else {
+					row![
+						text(format!(
+							"Paused here, resumes by itself after {:?}",
+							Duration::from_millis(breakpoint.timeout_millis)
+						)),
+						button("Continue").on_press(Message::ResumeBreakpoint(self.log.uuid.clone()))
+					]
+				},
+			);
+		}
+
+		// The environment of the process that sent the log, to reproduce it with.
+		if let Some(environment) = self
+			.metadata
+			.as_ref()
+			.and_then(|metadata| metadata.environment.as_ref())
+		{
+			trace = trace.push(checkbox(
+				"Show environment",
+				self.show_environment,
+				Message::ShowEnvironment,
+			));
+
+			if self.show_environment {
+				let mut lines = vec![
+					format!("Arguments: {}", environment.args.join(" ")),
+					format!("Directory: {}", environment.cwd),
+					format!(
+						"Built for {} with {} ({} profile)",
+						environment.target, environment.rustc_version, environment.profile
+					),
+				];
+
+				lines.extend(
+					environment
+						.variables
+						.iter()
+						.map(|(name, value)| format!("{name}={value}")),
+				);
 
-		container.into()
+				trace = trace.push(text(lines.join("\n")).font(Font::MONOSPACE));
+			}
+		}
+
+		// Logs of byte buffers carry the raw bytes, of which the message is only
+		// the start.
+		if let Some(binary) = self
+			.metadata
+			.as_ref()
+			.and_then(|metadata| metadata.binary.as_ref())
+		{
+			let rendered = self
+				.decoding
+				.render(&binary.data)
+				.unwrap_or_else(|error| format!("Could not decode as {}: {error}", self.decoding));
+
+			trace = trace
+				.push(row![
+					text(format!(
+						"{} bytes of {}, shown as",
+						binary.data.len(),
+						binary.content_type
+					)),
+					pick_list(
+						&Decoding::ALL[..],
+						Some(self.decoding),
+						Message::BinaryDecodingSelected
+					),
+				])
+				.push(scrollable(text(rendered).font(Font::MONOSPACE)));
+
+			return container(trace).into();
+		}
+
+		container(trace.push(text(&self.log.message))).into()
+	}
+
+	fn code_view(&self) -> iced::Element<'_, Message> {
+		let lines = self
+			.log
+			.code_snippet
+			.iter()
+			.map(|(line_number, code)| {
+				let marker = if *line_number == self.log.line_number {
+					">"
+				} else {
+					" "
+				};
+
+				text(format!("{marker} {line_number:>4} | {code}"))
+					.font(Font::MONOSPACE)
+					.into()
+			})
+			.collect::<Vec<_>>();
+
+		let mut code = column![];
+
+		if let Some(git) = self
+			.metadata
+			.as_ref()
+			.and_then(|metadata| metadata.git.as_ref())
+		{
+			code = code.push(text(format!("Commit: {} ({})", git.commit, git.branch)));
+		}
+
+		if let Some(warning) = self.snippet_warning() {
+			code = code.push(text(format!("Warning: {warning}")));
+		}
+
+		code = code.push(scrollable(column(lines)));
+
+		if let Some(diff) = &self.source_diff {
+			let diff = diff
+				.iter()
+				.map(|line| text(line).font(Font::MONOSPACE).into())
+				.collect();
+
+			code = code
+				.push(text(
+					"Warning: the file has changed since this log was created, current changes:",
+				))
+				.push(scrollable(column(diff)));
+		}
+
+		code.into()
 	}
-	// NOTE: temporary
-	#[allow(clippy::unused_self)]
-	fn code_view(&self) -> iced::Element<'_, Message> { column![].into() }
 }
 
 impl View for LogDetails {
@@ -40,6 +301,28 @@ impl View for LogDetails {
 				self.split_size = Some(size);
 				Command::none()
 			},
+			Message::ShowEnvironment(show) => {
+				self.show_environment = show;
+				Command::none()
+			},
+			Message::BinaryDecodingSelected(decoding) => {
+				self.decoding = decoding;
+				Command::none()
+			},
+			Message::LogPinned(uuid, pinned) => {
+				if uuid == self.log.uuid {
+					self.pinned = pinned;
+				}
+
+				Command::none()
+			},
+			Message::BreakpointResumed(uuid) => {
+				if uuid == self.log.uuid {
+					self.breakpoint_resumed = true;
+				}
+
+				Command::none()
+			},
 			_ => Command::none(),
 		}
 	}
@@ -26,6 +26,8 @@ impl LogItem {
 }
 
 impl LogItem {
+	pub fn log(&self) -> &Log { &self.log }
+
 	fn parse_log(mut log: Log) -> Log {
 		log.message = log.message.replace('\"', "");
 
@@ -1,5 +1,7 @@
+mod log_points_view;
 mod main_view;
 mod search_view;
 
+pub use log_points_view::*;
 pub use main_view::*;
 pub use search_view::*;
@@ -0,0 +1,20 @@
+// The header is checked in at `include/codectrl.h`, and is only regenerated
+// with the `generate-header` feature so that regular builds never write to the
+// source tree.
+#[cfg(feature = "generate-header")]
+fn main() {
+	use std::{env, path::Path};
+
+	let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+	let header = Path::new(&crate_dir).join("include/codectrl.h");
+
+	println!("cargo:rerun-if-changed=src/lib.rs");
+	println!("cargo:rerun-if-changed=cbindgen.toml");
+
+	cbindgen::generate(&crate_dir)
+		.expect("Could not generate the C header for codectrl-logger-ffi")
+		.write_to_file(header);
+}
+
+#[cfg(not(feature = "generate-header"))]
+fn main() {}
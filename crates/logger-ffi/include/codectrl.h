@@ -0,0 +1,94 @@
+#ifndef CODECTRL_H
+#define CODECTRL_H
+
+/* Generated from crates/logger-ffi by cbindgen. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+#define CODECTRL_LANGUAGE "C++"
+#else
+#define CODECTRL_LANGUAGE "C"
+#endif
+
+/* Sends a log of `message` from the current file, line and function. */
+#define CODECTRL_LOG(message) \
+  codectrl_log_language((message), CODECTRL_LANGUAGE, __FILE__, __LINE__, __func__)
+
+// The result of a call to one of the `codectrl_log` functions.
+typedef enum CodectrlStatus {
+  // The log was sent, or logging is disabled.
+  CODECTRL_STATUS_OK = 0,
+  // A required argument was null, or a string wasn't valid UTF-8.
+  CODECTRL_STATUS_INVALID_ARGUMENT = 1,
+  // The log couldn't be sent.
+  CODECTRL_STATUS_ERROR = 2,
+} CodectrlStatus;
+
+// Where a log was created, for `codectrl_log_at`. Every string other than
+// `language` may be null.
+typedef struct CodectrlLocation {
+  // The language that the log was created from, such as `"C++"`.
+  const char *language;
+  // The path of the source file, relative to the current directory.
+  const char *file;
+  uint32_t line;
+  const char *function;
+  // The source code around the log, which is sent as its code snippet
+  // instead of the lines read from `file`.
+  const char *snippet;
+  // The line number of the first line of `snippet`.
+  uint32_t snippet_first_line;
+} CodectrlLocation;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Sends a log of `message`, created in C at `line` of `file` in `function`.
+// `file` and `function` may be null. Usually called through the
+// `CODECTRL_LOG` macro.
+//
+// # Safety
+//
+// Every string must be null or point to a nul-terminated string.
+enum CodectrlStatus codectrl_log(const char *message,
+                                 const char *file,
+                                 uint32_t line,
+                                 const char *function);
+
+// Sends a log of `message`, created in `language` at `line` of `file` in
+// `function`. `file` and `function` may be null.
+//
+// # Safety
+//
+// Every string must be null or point to a nul-terminated string.
+enum CodectrlStatus codectrl_log_language(const char *message,
+                                          const char *language,
+                                          const char *file,
+                                          uint32_t line,
+                                          const char *function);
+
+// Sends a log of `message`, created at `location`. Unlike the other
+// functions, this allows the code snippet to be given by the caller.
+//
+// # Safety
+//
+// `location` must point to a valid `CodectrlLocation`, and every string must
+// be null or point to a nul-terminated string.
+enum CodectrlStatus codectrl_log_at(const char *message, const struct CodectrlLocation *location);
+
+// Returns a description of the last error on the calling thread, or null if
+// the last call succeeded. The string is valid until the next call to a
+// `codectrl_log` function on the same thread.
+const char *codectrl_last_error(void);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CODECTRL_H */
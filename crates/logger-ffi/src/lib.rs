@@ -0,0 +1,236 @@
+//! C bindings for `codectrl-logger`, so that logs can be sent to CodeCTRL from
+//! C, C++ and any other language that can call C functions. The header for
+//! these functions is in `include/codectrl.h`, and is regenerated when this
+//! crate is built with the `generate-header` feature.
+//!
+//! The logger is configured from the same environment variables as the Rust
+//! logger, such as `CODECTRL_HOST` and `CODECTRL_PORT`.
+
+use std::{
+	cell::RefCell,
+	ffi::{c_char, CStr, CString},
+	panic::{self, AssertUnwindSafe},
+	ptr,
+};
+
+use codectrl_logger::foreign::ForeignLog;
+
+#[cfg(test)]
+mod tests;
+
+thread_local! {
+	static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The result of a call to one of the `codectrl_log` functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodectrlStatus {
+	/// The log was sent, or logging is disabled.
+	Ok = 0,
+	/// A required argument was null, or a string wasn't valid UTF-8.
+	InvalidArgument = 1,
+	/// The log couldn't be sent.
+	Error = 2,
+}
+
+/// Where a log was created, for `codectrl_log_at`. Every string other than
+/// `language` may be null.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CodectrlLocation {
+	/// The language that the log was created from, such as `"C++"`.
+	pub language: *const c_char,
+	/// The path of the source file, relative to the current directory.
+	pub file: *const c_char,
+	pub line: u32,
+	pub function: *const c_char,
+	/// The source code around the log, which is sent as its code snippet
+	/// instead of the lines read from `file`.
+	pub snippet: *const c_char,
+	/// The line number of the first line of `snippet`.
+	pub snippet_first_line: u32,
+}
+
+// The arguments of a log, once converted from C strings.
+struct Arguments<'a> {
+	language: &'a str,
+	message: &'a str,
+	file: Option<&'a str>,
+	line: u32,
+	function: Option<&'a str>,
+	snippet: Option<(u32, &'a str)>,
+}
+
+/// Sends a log of `message`, created in C at `line` of `file` in `function`.
+/// `file` and `function` may be null. Usually called through the
+/// `CODECTRL_LOG` macro.
+///
+/// # Safety
+///
+/// Every string must be null or point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn codectrl_log(
+	message: *const c_char,
+	file: *const c_char,
+	line: u32,
+	function: *const c_char,
+) -> CodectrlStatus {
+	let arguments = || {
+		Ok(Arguments {
+			language: "C",
+			message: required_str(message, "message")?,
+			file: optional_str(file, "file")?,
+			line,
+			function: optional_str(function, "function")?,
+			snippet: None,
+		})
+	};
+
+	send(arguments())
+}
+
+/// Sends a log of `message`, created in `language` at `line` of `file` in
+/// `function`. `file` and `function` may be null.
+///
+/// # Safety
+///
+/// Every string must be null or point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn codectrl_log_language(
+	message: *const c_char,
+	language: *const c_char,
+	file: *const c_char,
+	line: u32,
+	function: *const c_char,
+) -> CodectrlStatus {
+	let arguments = || {
+		Ok(Arguments {
+			language: required_str(language, "language")?,
+			message: required_str(message, "message")?,
+			file: optional_str(file, "file")?,
+			line,
+			function: optional_str(function, "function")?,
+			snippet: None,
+		})
+	};
+
+	send(arguments())
+}
+
+/// Sends a log of `message`, created at `location`. Unlike the other
+/// functions, this allows the code snippet to be given by the caller.
+///
+/// # Safety
+///
+/// `location` must point to a valid `CodectrlLocation`, and every string must
+/// be null or point to a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn codectrl_log_at(
+	message: *const c_char,
+	location: *const CodectrlLocation,
+) -> CodectrlStatus {
+	let Some(location) = location.as_ref() else {
+		return invalid_argument("`location` is null");
+	};
+
+	let arguments = || {
+		Ok(Arguments {
+			language: required_str(location.language, "language")?,
+			message: required_str(message, "message")?,
+			file: optional_str(location.file, "file")?,
+			line: location.line,
+			function: optional_str(location.function, "function")?,
+			snippet: optional_str(location.snippet, "snippet")?
+				.map(|snippet| (location.snippet_first_line, snippet)),
+		})
+	};
+
+	send(arguments())
+}
+
+/// Returns a description of the last error on the calling thread, or null if
+/// the last call succeeded. The string is valid until the next call to a
+/// `codectrl_log` function on the same thread.
+#[no_mangle]
+pub extern "C" fn codectrl_last_error() -> *const c_char {
+	LAST_ERROR.with(|error| {
+		error
+			.borrow()
+			.as_ref()
+			.map_or(ptr::null(), |error| error.as_ptr())
+	})
+}
+
+fn send(arguments: Result<Arguments<'_>, CodectrlStatus>) -> CodectrlStatus {
+	let arguments = match arguments {
+		Ok(arguments) => arguments,
+		Err(status) => return status,
+	};
+
+	// Unwinding into C is undefined behaviour.
+	let result = panic::catch_unwind(AssertUnwindSafe(|| {
+		let mut log = ForeignLog::new(arguments.language, arguments.message);
+
+		if let Some(file) = arguments.file {
+			log = log.location(file, arguments.line);
+		}
+
+		if let Some(function) = arguments.function {
+			log = log.function(function);
+		}
+
+		if let Some((first_line, snippet)) = arguments.snippet {
+			log = log.snippet(first_line, snippet);
+		}
+
+		log.send()
+	}));
+
+	match result {
+		Ok(Ok(())) => {
+			set_last_error(None);
+			CodectrlStatus::Ok
+		},
+		Ok(Err(error)) => {
+			set_last_error(Some(error.to_string()));
+			CodectrlStatus::Error
+		},
+		Err(_) => {
+			set_last_error(Some("The logger panicked".to_string()));
+			CodectrlStatus::Error
+		},
+	}
+}
+
+// Safety: `pointer` must be null or point to a nul-terminated string.
+unsafe fn optional_str<'a>(
+	pointer: *const c_char,
+	name: &str,
+) -> Result<Option<&'a str>, CodectrlStatus> {
+	if pointer.is_null() {
+		return Ok(None);
+	}
+
+	CStr::from_ptr(pointer)
+		.to_str()
+		.map(Some)
+		.map_err(|_| invalid_argument(&format!("`{name}` is not valid UTF-8")))
+}
+
+// Safety: `pointer` must be null or point to a nul-terminated string.
+unsafe fn required_str<'a>(pointer: *const c_char, name: &str) -> Result<&'a str, CodectrlStatus> {
+	optional_str(pointer, name)?.ok_or_else(|| invalid_argument(&format!("`{name}` is null")))
+}
+
+fn invalid_argument(error: &str) -> CodectrlStatus {
+	set_last_error(Some(error.to_string()));
+	CodectrlStatus::InvalidArgument
+}
+
+fn set_last_error(error: Option<String>) {
+	// Errors can't contain nul bytes once given to C.
+	let error = error.map(|error| CString::new(error.replace('\0', "")).unwrap_or_default());
+
+	LAST_ERROR.with(|last_error| *last_error.borrow_mut() = error);
+}
@@ -0,0 +1,42 @@
+use std::{
+	ffi::{CStr, CString},
+	ptr,
+};
+
+use crate::{codectrl_last_error, codectrl_log, codectrl_log_at, CodectrlStatus};
+
+fn last_error() -> String {
+	let error = codectrl_last_error();
+	assert!(!error.is_null());
+
+	unsafe { CStr::from_ptr(error) }
+		.to_str()
+		.unwrap()
+		.to_string()
+}
+
+#[test]
+fn null_message() {
+	let status = unsafe { codectrl_log(ptr::null(), ptr::null(), 0, ptr::null()) };
+
+	assert_eq!(status, CodectrlStatus::InvalidArgument);
+	assert_eq!(last_error(), "`message` is null");
+}
+
+#[test]
+fn invalid_utf8() {
+	let message = b"\xff\xfe\0";
+	let status = unsafe { codectrl_log(message.as_ptr().cast(), ptr::null(), 0, ptr::null()) };
+
+	assert_eq!(status, CodectrlStatus::InvalidArgument);
+	assert_eq!(last_error(), "`message` is not valid UTF-8");
+}
+
+#[test]
+fn null_location() {
+	let message = CString::new("message").unwrap();
+	let status = unsafe { codectrl_log_at(message.as_ptr(), ptr::null()) };
+
+	assert_eq!(status, CodectrlStatus::InvalidArgument);
+	assert_eq!(last_error(), "`location` is null");
+}
@@ -0,0 +1,256 @@
+//! Procedural macros for `codectrl-logger`. These are re-exported by
+//! `codectrl-logger` and should be used from there.
+
+// rustfmt indents the code in doc comments with hard tabs.
+#![allow(clippy::tabs_in_doc_comments)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::{
+	parse::Parser,
+	parse_macro_input, parse_quote,
+	punctuated::Punctuated,
+	spanned::Spanned,
+	visit_mut::{self, VisitMut},
+	Expr, ExprReturn, ExprTry, FnArg, Ident, Item, ItemFn, Meta, Pat, ReturnType, Signature, Token,
+	Type,
+};
+
+/// Logs entry to the annotated function with the [`Debug`] value of each of
+/// its arguments, and exit from it with its return value and how long it took.
+///
+/// Both logs share a call ID, which CodeCTRL uses to pair them. Every argument
+/// other than `self` must implement [`Debug`], as must the return value.
+/// Arguments can be left out with `skip`:
+///
+/// ```ignore
+/// #[codectrl_logger::instrument(skip(password))]
+/// fn login(username: &str, password: &str) -> bool { todo!() }
+/// ```
+///
+/// The exit is logged however the function returns, including through
+/// `return` or `?`, except from inside of another macro such as `format!`.
+/// `async` and `const` functions are not supported.
+#[proc_macro_attribute]
+pub fn instrument(attribute: TokenStream, item: TokenStream) -> TokenStream {
+	let skipped = match parse_skipped(attribute.into()) {
+		Ok(skipped) => skipped,
+		Err(error) => return error.into_compile_error().into(),
+	};
+
+	let function = parse_macro_input!(item as ItemFn);
+
+	instrument_function(function, &skipped)
+		.unwrap_or_else(syn::Error::into_compile_error)
+		.into()
+}
+
+/// Runs the annotated function as a test, in place of `#[test]`, tagging
+/// every log it creates with the name of the test. Once the test finishes,
+/// whether it passed is reported to CodeCTRL, which groups the logs of each
+/// test together.
+///
+/// ```ignore
+/// #[codectrl_logger::test]
+/// fn parses_config() {
+/// 	codectrl_logger::log!("Parsing...").unwrap();
+/// }
+/// ```
+///
+/// Tests can return `()` or a `Result`, and can be combined with
+/// `#[should_panic]`. `async` functions are not supported.
+#[proc_macro_attribute]
+pub fn test(attribute: TokenStream, item: TokenStream) -> TokenStream {
+	if !attribute.is_empty() {
+		return syn::Error::new(
+			TokenStream2::from(attribute).span(),
+			"`test` doesn't take any arguments",
+		)
+		.into_compile_error()
+		.into();
+	}
+
+	let function = parse_macro_input!(item as ItemFn);
+
+	test_function(function)
+		.unwrap_or_else(syn::Error::into_compile_error)
+		.into()
+}
+
+// Parses the `skip(a, b)` list of arguments that shouldn't be logged.
+fn parse_skipped(attribute: TokenStream2) -> syn::Result<Vec<Ident>> {
+	let metas = Punctuated::<Meta, Token![,]>::parse_terminated.parse2(attribute)?;
+	let mut skipped = vec![];
+
+	for meta in metas {
+		match meta {
+			Meta::List(list) if list.path.is_ident("skip") =>
+				skipped.extend(list.parse_args_with(Punctuated::<Ident, Token![,]>::parse_terminated)?),
+			meta => return Err(syn::Error::new(meta.span(), "expected `skip(...)`")),
+		}
+	}
+
+	Ok(skipped)
+}
+
+fn instrument_function(mut function: ItemFn, skipped: &[Ident]) -> syn::Result<TokenStream2> {
+	reject_async(&function.sig, "instrument")?;
+
+	if let Some(constness) = function.sig.constness {
+		return Err(syn::Error::new(
+			constness.span(),
+			"`instrument` does not support const functions",
+		));
+	}
+
+	let name = function.sig.ident.to_string();
+
+	// Only arguments bound to a plain identifier can be logged.
+	let arguments = function
+		.sig
+		.inputs
+		.iter()
+		.filter_map(|input| match input {
+			FnArg::Typed(argument) => match argument.pat.as_ref() {
+				Pat::Ident(pattern) => Some(&pattern.ident),
+				_ => None,
+			},
+			FnArg::Receiver(_) => None,
+		})
+		.filter(|argument| !skipped.contains(argument))
+		.collect::<Vec<_>>();
+
+	// The body is run as a labelled block rather than a closure, so that it can
+	// still borrow the arguments and return borrows with elided lifetimes.
+	let return_type = match &function.sig.output {
+		ReturnType::Type(_, ty) if contains_impl_trait(ty) => quote!(),
+		ReturnType::Type(_, ty) => quote!(: #ty),
+		ReturnType::Default => quote!(: ()),
+	};
+	let mut block = function.block.clone();
+	EarlyReturns.visit_block_mut(&mut block);
+	let span = function.sig.span();
+
+	let body = quote_spanned! {span=>
+		{
+			let __codectrl_call = ::codectrl_logger::call::Call::enter(
+				#name,
+				&[#((::core::stringify!(#arguments), &#arguments as &dyn ::std::fmt::Debug)),*],
+			);
+			#[allow(unused_labels, clippy::let_unit_value)]
+			let __codectrl_return #return_type = '__codectrl_body: #block;
+			__codectrl_call.exit(&__codectrl_return);
+			__codectrl_return
+		}
+	};
+
+	*function.block = syn::parse2(body)?;
+
+	Ok(quote!(#function))
+}
+
+fn test_function(mut function: ItemFn) -> syn::Result<TokenStream2> {
+	reject_async(&function.sig, "test")?;
+
+	let name = function.sig.ident.to_string();
+	let should_panic = function
+		.attrs
+		.iter()
+		.any(|attribute| attribute.path().is_ident("should_panic"))
+		.then(|| quote!(.should_panic()));
+
+	let return_type = closure_return_type(&function.sig);
+	let block = &function.block;
+	let span = function.sig.span();
+
+	let body = quote_spanned! {span=>
+		{
+			let __codectrl_test =
+				::codectrl_logger::test_case::TestCase::for_function(::core::module_path!(), #name)
+					#should_panic;
+			#[allow(clippy::redundant_closure_call, clippy::let_unit_value)]
+			let __codectrl_return = (move || #return_type #block)();
+			__codectrl_test.finish(::codectrl_logger::test_case::Outcome::passed(&__codectrl_return));
+			__codectrl_return
+		}
+	};
+
+	*function.block = syn::parse2(body)?;
+
+	Ok(quote! {
+		#[test]
+		#function
+	})
+}
+
+// Turns every `return` and `?` of an instrumented function into a break out of
+// its body, so that the exit from it is still logged. Closures, async blocks and
+// nested items return from themselves, so they are left alone.
+struct EarlyReturns;
+
+impl VisitMut for EarlyReturns {
+	fn visit_expr_mut(&mut self, expr: &mut Expr) {
+		if matches!(expr, Expr::Closure(_) | Expr::Async(_)) {
+			return;
+		}
+
+		visit_mut::visit_expr_mut(self, expr);
+
+		match expr {
+			Expr::Return(ExprReturn { expr: None, .. }) => *expr = parse_quote!(break '__codectrl_body),
+			Expr::Return(ExprReturn {
+				expr: Some(value), ..
+			}) => {
+				*expr = parse_quote! {
+					{
+						let __codectrl_value = #value;
+						break '__codectrl_body __codectrl_value
+					}
+				};
+			},
+			Expr::Try(ExprTry { expr: value, .. }) => {
+				*expr = parse_quote! {
+					match ::codectrl_logger::call::Try::branch(#value) {
+						::core::ops::ControlFlow::Continue(__codectrl_value) => __codectrl_value,
+						::core::ops::ControlFlow::Break(__codectrl_residual) => {
+							let __codectrl_value =
+								::codectrl_logger::call::FromResidual::from_residual(__codectrl_residual);
+							break '__codectrl_body __codectrl_value
+						},
+					}
+				};
+			},
+			_ => {},
+		}
+	}
+
+	fn visit_item_mut(&mut self, _: &mut Item) {}
+}
+
+fn reject_async(signature: &Signature, attribute: &str) -> syn::Result<()> {
+	match signature.asyncness {
+		Some(asyncness) => Err(syn::Error::new(
+			asyncness.span(),
+			format!("`{attribute}` does not support async functions"),
+		)),
+		None => Ok(()),
+	}
+}
+
+// The closure's return type has to be given for `?` to work inside of it, but
+// `impl Trait` isn't allowed there.
+fn closure_return_type(signature: &Signature) -> TokenStream2 {
+	match &signature.output {
+		ReturnType::Type(_, ty) if contains_impl_trait(ty) => quote!(),
+		ReturnType::Type(_, ty) => quote!(-> #ty),
+		ReturnType::Default => quote!(-> ()),
+	}
+}
+
+fn contains_impl_trait(ty: &Type) -> bool {
+	quote!(#ty)
+		.to_string()
+		.split_whitespace()
+		.any(|token| token == "impl")
+}
@@ -0,0 +1,27 @@
+use std::{env, process::Command};
+
+// Records the toolchain that the logger is built with, which is the same as
+// that of the binary it is linked into, for the environment snapshot.
+fn main() {
+	let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+	let rustc_version = Command::new(rustc)
+		.arg("--version")
+		.output()
+		.ok()
+		.and_then(|output| String::from_utf8(output.stdout).ok())
+		.unwrap_or_default();
+
+	println!(
+		"cargo:rustc-env=CODECTRL_BUILD_TARGET={}",
+		env::var("TARGET").unwrap_or_default()
+	);
+	println!(
+		"cargo:rustc-env=CODECTRL_BUILD_PROFILE={}",
+		env::var("PROFILE").unwrap_or_default()
+	);
+	println!(
+		"cargo:rustc-env=CODECTRL_BUILD_RUSTC_VERSION={}",
+		rustc_version.trim()
+	);
+	println!("cargo:rerun-if-env-changed=RUSTC");
+}
@@ -0,0 +1,151 @@
+//! Support for the assertion macros, such as [`assert_eq!`], which send a log
+//! describing the failed assertion before panicking.
+//!
+//! [`assert_eq!`]: crate::assert_eq
+
+use std::{
+	fmt::{self, Debug},
+	thread,
+};
+
+use similar::TextDiff;
+
+use crate::{create_log, Logger, LoggerConfig};
+
+/// The comparison made by [`assert_eq!`] or [`assert_ne!`].
+///
+/// [`assert_eq!`]: crate::assert_eq
+/// [`assert_ne!`]: crate::assert_ne
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub enum Comparison {
+	Eq,
+	Ne,
+}
+
+impl Comparison {
+	/// Whether the assertion failed, given whether the operands were equal.
+	pub fn failed(self, equal: bool) -> bool {
+		match self {
+			Self::Eq => !equal,
+			Self::Ne => equal,
+		}
+	}
+
+	fn operator(self) -> &'static str {
+		match self {
+			Self::Eq => "==",
+			Self::Ne => "!=",
+		}
+	}
+}
+
+// The message of the log sent for a failed assertion.
+struct Failure {
+	expression: String,
+	message: Option<String>,
+	operands: Option<Operands>,
+}
+
+struct Operands {
+	left: String,
+	right: String,
+}
+
+impl Debug for Failure {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "assertion failed: {}", self.expression)?;
+
+		if let Some(message) = &self.message {
+			write!(f, "\n\n{message}")?;
+		}
+
+		if let Some(Operands { left, right }) = &self.operands {
+			write!(f, "\n\nleft:\n{left}\n\nright:\n{right}")?;
+
+			if left != right {
+				let diff = TextDiff::from_lines(left, right)
+					.unified_diff()
+					.header("left", "right")
+					.missing_newline_hint(false)
+					.to_string();
+
+				write!(f, "\n\ndiff:\n{diff}")?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Reports that `expression` was false, then panics.
+#[doc(hidden)]
+#[track_caller]
+pub fn failed(expression: &'static str, message: Option<fmt::Arguments<'_>>) -> ! {
+	report(Failure {
+		expression: expression.to_string(),
+		message: message.map(|message| message.to_string()),
+		operands: None,
+	});
+
+	match message {
+		Some(message) => panic!("{message}"),
+		None => panic!("assertion failed: {expression}"),
+	}
+}
+
+/// Reports that `left` and `right` didn't compare as expected, then panics.
+#[doc(hidden)]
+#[track_caller]
+pub fn compare_failed<L: Debug + ?Sized, R: Debug + ?Sized>(
+	comparison: Comparison,
+	expressions: (&'static str, &'static str),
+	left: &L,
+	right: &R,
+	message: Option<fmt::Arguments<'_>>,
+) -> ! {
+	let operator = comparison.operator();
+
+	report(Failure {
+		expression: format!("{} {operator} {}", expressions.0, expressions.1),
+		message: message.map(|message| message.to_string()),
+		operands: Some(Operands {
+			left: format!("{left:#?}"),
+			right: format!("{right:#?}"),
+		}),
+	});
+
+	// The same message as the standard library's assertions.
+	match message {
+		Some(message) => panic!(
+			"assertion `left {operator} right` failed: {message}\n  left: {left:?}\n right: {right:?}"
+		),
+		None => panic!("assertion `left {operator} right` failed\n  left: {left:?}\n right: {right:?}"),
+	}
+}
+
+fn report(failure: Failure) {
+	if !Logger::is_enabled() {
+		return;
+	}
+
+	let config = LoggerConfig::global();
+
+	// Failed assertions aren't sampled, as they are about to end the thread.
+	let Some(log) = create_log(failure, Some(config.surround), None, None, None) else {
+		return;
+	};
+
+	// Sent from a new thread, as the assertion may be inside of a tokio runtime,
+	// where blocking on a new runtime would panic.
+	let result = thread::spawn(move || {
+		config
+			.block_on(Logger::_log(&log, config))
+			.and_then(|result| result)
+	})
+	.join();
+
+	if let Ok(Err(error)) = result {
+		eprintln!("Could not log the failed assertion: {error}");
+	}
+}
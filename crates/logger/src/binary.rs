@@ -0,0 +1,74 @@
+//! Logs of byte buffers, created by [`Logger::log_bytes`]. The raw bytes are
+//! sent in the log's metadata, and the message is a hex dump of the start of
+//! the buffer for servers and GUIs that don't know about the metadata.
+//!
+//! [`Logger::log_bytes`]: crate::Logger::log_bytes
+
+use std::fmt::{self, Debug};
+
+use codectrl_protobuf_bindings::extensions::{BinaryPayload, ExtendedLog};
+
+/// The content type of bytes logged without one.
+pub(crate) const OCTET_STREAM: &str = "application/octet-stream";
+
+// How many bytes are included in the hex dump in the message.
+const MESSAGE_BYTES: usize = 256;
+const BYTES_PER_LINE: usize = 16;
+
+/// The message of a log of a byte buffer: a hex dump in the same format as
+/// `hexdump -C`, with an offset, the hex value of each byte, and the printable
+/// ASCII characters on each line.
+pub(crate) struct HexDump<'a>(pub &'a [u8]);
+
+impl Debug for HexDump<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let shown = &self.0[..self.0.len().min(MESSAGE_BYTES)];
+
+		for (index, line) in shown.chunks(BYTES_PER_LINE).enumerate() {
+			write!(f, "{:08x} ", index * BYTES_PER_LINE)?;
+
+			for column in 0..BYTES_PER_LINE {
+				if column % 8 == 0 {
+					f.write_str(" ")?;
+				}
+
+				match line.get(column) {
+					Some(byte) => write!(f, "{byte:02x} ")?,
+					None => f.write_str("   ")?,
+				}
+			}
+
+			let ascii = line
+				.iter()
+				.map(|byte| {
+					if byte.is_ascii_graphic() || *byte == b' ' {
+						char::from(*byte)
+					} else {
+						'.'
+					}
+				})
+				.collect::<String>();
+
+			writeln!(f, " |{ascii}|")?;
+		}
+
+		match self.0.len() - shown.len() {
+			0 => write!(f, "{} bytes", self.0.len()),
+			hidden => write!(f, "{} bytes, {hidden} not shown", self.0.len()),
+		}
+	}
+}
+
+/// Attaches the raw `bytes` to `log`.
+pub(crate) fn attach(log: &mut ExtendedLog, bytes: &[u8], content_type: &str) {
+	if let Some(log) = log.log.as_mut() {
+		log.message_type = String::from("&[u8]");
+	}
+
+	if let Some(metadata) = log.metadata.as_mut() {
+		metadata.binary = Some(BinaryPayload {
+			data: bytes.to_vec(),
+			content_type: content_type.to_string(),
+		});
+	}
+}
@@ -0,0 +1,213 @@
+//! Linked entry and exit logs for a single call to a function, as created by
+//! [`instrument`].
+//!
+//! [`instrument`]: crate::instrument
+
+use std::{
+	convert::Infallible,
+	fmt::{self, Debug},
+	ops::ControlFlow,
+	time::{Duration, Instant},
+};
+
+use uuid::Uuid;
+
+use codectrl_protobuf_bindings::extensions::{CallEvent, CallMetadata};
+
+use crate::{create_log, Logger, LoggerConfig};
+
+struct Arguments<'a> {
+	function: &'static str,
+	arguments: &'a [(&'a str, &'a dyn Debug)],
+}
+
+impl Debug for Arguments<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut debug = f.debug_struct(self.function);
+
+		for (name, value) in self.arguments {
+			debug.field(name, value);
+		}
+
+		debug.finish()
+	}
+}
+
+struct Returned<'a, T> {
+	function: &'static str,
+	value: &'a T,
+	elapsed: Duration,
+}
+
+impl<T: Debug> Debug for Returned<'_, T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct(self.function)
+			.field("returned", self.value)
+			.field("elapsed", &self.elapsed)
+			.finish()
+	}
+}
+
+/// A call to a function that is in progress. Created by [`Call::enter`],
+/// which logs the entry to the function, and finished by [`Call::exit`], which
+/// logs its return value.
+///
+/// This is used by the code generated by [`instrument`], which should be
+/// preferred over using it directly.
+///
+/// [`instrument`]: crate::instrument
+#[derive(Debug)]
+#[must_use = "the exit from the function is only logged by `Call::exit`"]
+pub struct Call {
+	function: &'static str,
+	call_id: String,
+	entry_id: String,
+	exit_id: String,
+	start: Instant,
+}
+
+impl Call {
+	/// Logs the entry to `function`, called with `arguments`.
+	pub fn enter(function: &'static str, arguments: &[(&str, &dyn Debug)]) -> Self {
+		// The ID of the exit log is decided up-front, so that the entry log can
+		// refer to it.
+		let call = Self {
+			function,
+			call_id: new_id(),
+			entry_id: new_id(),
+			exit_id: new_id(),
+			start: Instant::now(),
+		};
+
+		if Logger::is_enabled() {
+			call.send(
+				Arguments {
+					function,
+					arguments,
+				},
+				CallEvent::Entry,
+				None,
+			);
+		}
+
+		Self {
+			start: Instant::now(),
+			..call
+		}
+	}
+
+	/// Logs the exit from the function with its return value, `value`.
+	pub fn exit<T: Debug>(self, value: &T) {
+		let elapsed = self.start.elapsed();
+
+		if Logger::is_enabled() {
+			self.send(
+				Returned {
+					function: self.function,
+					value,
+					elapsed,
+				},
+				CallEvent::Exit,
+				Some(elapsed),
+			);
+		}
+	}
+
+	fn send<T: Debug>(&self, message: T, event: CallEvent, elapsed: Option<Duration>) {
+		let config = LoggerConfig::global();
+
+		// Calls aren't sampled, as that could separate the entry log from the exit
+		// log.
+		let Some(mut extended_log) = create_log(message, Some(config.surround), None, None, None)
+		else {
+			return;
+		};
+
+		let (id, paired_log_id) = match event {
+			CallEvent::Entry => (&self.entry_id, &self.exit_id),
+			CallEvent::Exit => (&self.exit_id, &self.entry_id),
+		};
+
+		if let Some(log) = extended_log.log.as_mut() {
+			log.uuid = id.clone();
+		}
+
+		if let Some(metadata) = extended_log.metadata.as_mut() {
+			metadata.call = Some(CallMetadata {
+				call_id: self.call_id.clone(),
+				event: event.into(),
+				function: self.function.to_string(),
+				paired_log_id: paired_log_id.clone(),
+				elapsed_nanos: elapsed.map(|elapsed| u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)),
+			});
+		}
+
+		let result = config
+			.block_on(Logger::_log(&extended_log, config))
+			.and_then(|result| result);
+
+		if let Err(error) = result {
+			eprintln!("Could not log call to {}: {error}", self.function);
+		}
+	}
+}
+
+/// Used in place of the `?` operator by the code generated by [`instrument`],
+/// so that the exit from the function is logged when `?` returns early. This
+/// mirrors the unstable [`std::ops::Try`], for the types that `?` can be used
+/// with on stable Rust.
+///
+/// [`instrument`]: crate::instrument
+#[doc(hidden)]
+pub trait Try {
+	type Output;
+	type Residual;
+
+	fn branch(self) -> ControlFlow<Self::Residual, Self::Output>;
+}
+
+/// Converts the early return of [`Try::branch`] into the return type of the
+/// function, in the same way as `?` does.
+#[doc(hidden)]
+pub trait FromResidual<R> {
+	fn from_residual(residual: R) -> Self;
+}
+
+impl<T, E> Try for Result<T, E> {
+	type Output = T;
+	type Residual = Result<Infallible, E>;
+
+	fn branch(self) -> ControlFlow<Self::Residual, T> {
+		match self {
+			Ok(value) => ControlFlow::Continue(value),
+			Err(error) => ControlFlow::Break(Err(error)),
+		}
+	}
+}
+
+impl<T, E, F: From<E>> FromResidual<Result<Infallible, E>> for Result<T, F> {
+	fn from_residual(residual: Result<Infallible, E>) -> Self {
+		match residual {
+			Ok(never) => match never {},
+			Err(error) => Err(F::from(error)),
+		}
+	}
+}
+
+impl<T> Try for Option<T> {
+	type Output = T;
+	type Residual = Option<Infallible>;
+
+	fn branch(self) -> ControlFlow<Self::Residual, T> {
+		match self {
+			Some(value) => ControlFlow::Continue(value),
+			None => ControlFlow::Break(None),
+		}
+	}
+}
+
+impl<T> FromResidual<Option<Infallible>> for Option<T> {
+	fn from_residual(_: Option<Infallible>) -> Self { None }
+}
+
+fn new_id() -> String { Uuid::new_v4().hyphenated().to_string() }
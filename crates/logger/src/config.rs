@@ -0,0 +1,278 @@
+//! Connection settings shared by every [`Logger`] function and [`LogBatch`].
+//!
+//! [`Logger`]: crate::Logger
+//! [`LogBatch`]: crate::LogBatch
+
+use std::{env, future::Future, sync::OnceLock, time::Duration};
+
+use tokio::runtime::{Handle, Runtime};
+
+use crate::{discovery, LoggerResult, Sampling, TlsConfig};
+
+static GLOBAL_CONFIG: OnceLock<LoggerConfig> = OnceLock::new();
+// Smaller requests aren't worth the time spent compressing them.
+const DEFAULT_COMPRESSION_THRESHOLD: usize = 4 * 1024;
+const DEFAULT_BREAKPOINT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// The settings used to connect to a CodeCTRL server and to create logs.
+///
+/// A global [`LoggerConfig`] is used by every [`Logger`] function and as the
+/// starting point for every [`LogBatch`]. It can be set once with
+/// [`LoggerConfig::set_global`], otherwise it is read from the environment
+/// with [`LoggerConfig::from_env`] on first use.
+///
+/// ```no_run
+/// use codectrl_logger::{Logger, LoggerConfig};
+///
+/// LoggerConfig::from_env()
+/// 	.host("10.0.0.5")
+/// 	.surround(5)
+/// 	.set_global()
+/// 	.unwrap();
+///
+/// Logger::log("Hello, world!", None, None, None, None).unwrap();
+/// ```
+///
+/// [`Logger`]: crate::Logger
+/// [`LogBatch`]: crate::LogBatch
+#[derive(Debug, Clone)]
+pub struct LoggerConfig {
+	pub(crate) host: String,
+	pub(crate) port: String,
+	pub(crate) timeout: Option<Duration>,
+	pub(crate) surround: u32,
+	pub(crate) tokio_runtime: Option<Handle>,
+	pub(crate) token: Option<String>,
+	pub(crate) tls: Option<TlsConfig>,
+	pub(crate) sampling: Option<Sampling>,
+	pub(crate) compression_threshold: Option<usize>,
+	pub(crate) remote_log_points: bool,
+	pub(crate) breakpoint_timeout: Duration,
+	pub(crate) environment_allowlist: Option<Vec<String>>,
+	pub(crate) redact_secret_environment: bool,
+	pub(crate) discover: bool,
+}
+
+impl Default for LoggerConfig {
+	fn default() -> Self {
+		Self {
+			host: String::from("127.0.0.1"),
+			port: String::from("3002"),
+			timeout: None,
+			surround: 3,
+			tokio_runtime: None,
+			token: None,
+			tls: None,
+			sampling: None,
+			compression_threshold: Some(DEFAULT_COMPRESSION_THRESHOLD),
+			remote_log_points: false,
+			breakpoint_timeout: DEFAULT_BREAKPOINT_TIMEOUT,
+			environment_allowlist: None,
+			redact_secret_environment: true,
+			discover: false,
+		}
+	}
+}
+
+impl LoggerConfig {
+	/// Creates a [`LoggerConfig`] that connects to `127.0.0.1:3002`.
+	pub fn new() -> Self { Self::default() }
+
+	/// Creates a [`LoggerConfig`] from the `CODECTRL_HOST`, `CODECTRL_PORT` and
+	/// `CODECTRL_TOKEN` environment variables. Setting `CODECTRL_LOG_POINTS`
+	/// enables [`LoggerConfig::remote_log_points`], and `CODECTRL_ENVIRONMENT`
+	/// is read as a comma-separated allowlist for
+	/// [`LoggerConfig::environment_snapshot`].
+	///
+	/// If neither `CODECTRL_HOST` nor `CODECTRL_PORT` are set, the host and
+	/// port of a server running on this machine are read from its discovery
+	/// file, falling back to the defaults of [`LoggerConfig::new`] if there
+	/// isn't one. The discovery file is read again whenever the server can't
+	/// be reached, unless [`LoggerConfig::host`] or [`LoggerConfig::port`] are
+	/// called afterwards.
+	pub fn from_env() -> Self {
+		let var = |name| {
+			env::var(name)
+				.ok()
+				.filter(|value: &String| !value.is_empty())
+		};
+		let mut config = Self::default();
+
+		match (var("CODECTRL_HOST"), var("CODECTRL_PORT")) {
+			(None, None) => {
+				config.discover = true;
+
+				if let Some((host, port)) = discovery::discover() {
+					config.host = host;
+					config.port = port.to_string();
+				}
+			},
+			(host, port) => {
+				if let Some(host) = host {
+					config.host = host;
+				}
+
+				if let Some(port) = port {
+					config.port = port;
+				}
+			},
+		}
+
+		config.token = var("CODECTRL_TOKEN");
+		config.remote_log_points = var("CODECTRL_LOG_POINTS").is_some();
+		config.environment_allowlist = var("CODECTRL_ENVIRONMENT").map(|allowlist| {
+			allowlist
+				.split(',')
+				.map(str::trim)
+				.filter(|name| !name.is_empty())
+				.map(String::from)
+				.collect()
+		});
+
+		config
+	}
+
+	/// Returns the global [`LoggerConfig`], initialising it with
+	/// [`LoggerConfig::from_env`] if [`LoggerConfig::set_global`] hasn't been
+	/// called.
+	pub fn global() -> &'static Self { GLOBAL_CONFIG.get_or_init(Self::from_env) }
+
+	/// Sets the global [`LoggerConfig`] used by every logging function.
+	///
+	/// The global config can only be set once, and must be set before the first
+	/// log is sent. If it has already been set, `self` is returned as the
+	/// error.
+	pub fn set_global(self) -> Result<(), Self> { GLOBAL_CONFIG.set(self) }
+
+	/// Sets the host of the gRPC server to connect to. A host starting with
+	/// `https://` is connected to over TLS.
+	pub fn host(mut self, host: impl Into<String>) -> Self {
+		self.host = host.into();
+		self.discover = false;
+		self
+	}
+
+	/// Sets the port of the `host` gRPC server to connect to.
+	pub fn port(mut self, port: impl ToString) -> Self {
+		self.port = port.to_string();
+		self.discover = false;
+		self
+	}
+
+	/// Sets how long to wait for the server to connect and to respond to each
+	/// request before giving up. By default, there is no timeout.
+	pub fn timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = Some(timeout);
+		self
+	}
+
+	/// Sets the default surround for generated code snippets.
+	pub fn surround(mut self, surround: u32) -> Self {
+		self.surround = surround;
+		self
+	}
+
+	/// If a tokio runtime is already present, you can pass it here so that a
+	/// new tokio runtime is not created every time a log is sent.
+	pub fn tokio_runtime(mut self, handle: Handle) -> Self {
+		self.tokio_runtime = Some(handle);
+		self
+	}
+
+	/// Sets the API token that is sent to the gRPC server, for servers that
+	/// require authentication.
+	pub fn token(mut self, token: impl Into<String>) -> Self {
+		self.token = Some(token.into());
+		self
+	}
+
+	/// Connects to the gRPC server over TLS using `tls`.
+	pub fn tls(mut self, tls: TlsConfig) -> Self {
+		self.tls = Some(tls);
+		self
+	}
+
+	/// Sets how many logs are sent from each call site. See [`Sampling`].
+	pub fn sampling(mut self, sampling: Sampling) -> Self {
+		self.sampling = Some(sampling);
+		self
+	}
+
+	/// Compresses requests with gzip once they are at least `threshold` bytes
+	/// when encoded. Defaults to 4 KiB.
+	///
+	/// If the server doesn't accept gzip, the request is resent uncompressed.
+	/// Every log sent through a [`LogStream`] shares a single request, which is
+	/// only compressed when `threshold` is `0`.
+	///
+	/// [`LogStream`]: crate::LogStream
+	pub fn compression_threshold(mut self, threshold: usize) -> Self {
+		self.compression_threshold = Some(threshold);
+		self
+	}
+
+	/// Never compresses requests.
+	pub fn disable_compression(mut self) -> Self {
+		self.compression_threshold = None;
+		self
+	}
+
+	/// Lets call sites be enabled, disabled and sampled from the CodeCTRL
+	/// GUI while the program is running, without recompiling it.
+	///
+	/// Each call site is registered with the server the first time it logs, and
+	/// its rule is kept up-to-date from a background thread. This only has an
+	/// effect on the global [`LoggerConfig`].
+	pub fn remote_log_points(mut self) -> Self {
+		self.remote_log_points = true;
+		self
+	}
+
+	/// Sets how long [`Logger::pause_here`] waits to be resumed from the GUI
+	/// before resuming by itself. Defaults to 5 minutes.
+	///
+	/// [`Logger::pause_here`]: crate::Logger::pause_here
+	pub fn breakpoint_timeout(mut self, timeout: Duration) -> Self {
+		self.breakpoint_timeout = timeout;
+		self
+	}
+
+	/// Sends a snapshot of the process's environment with the first log: the
+	/// environment variables whose names are in `allowlist`, along with the
+	/// command-line arguments, current directory, target triple, rustc version
+	/// and cargo profile. Names ending in `*` match every variable starting
+	/// with the rest of the name, such as `RUST_*`.
+	///
+	/// The values of variables and arguments whose names look secret, such as
+	/// `API_TOKEN`, are redacted unless disabled with
+	/// [`LoggerConfig::redact_secret_environment`]. This only has an effect on
+	/// the global [`LoggerConfig`].
+	pub fn environment_snapshot<S: Into<String>>(
+		mut self,
+		allowlist: impl IntoIterator<Item = S>,
+	) -> Self {
+		self.environment_allowlist = Some(allowlist.into_iter().map(Into::into).collect());
+		self
+	}
+
+	/// Sets whether the values of secret-looking environment variables and
+	/// arguments are redacted from the environment snapshot. Defaults to
+	/// `true`.
+	pub fn redact_secret_environment(mut self, redact: bool) -> Self {
+		self.redact_secret_environment = redact;
+		self
+	}
+
+	// Runs `future` to completion on the configured tokio runtime, or on a new
+	// runtime if there isn't one.
+	//
+	// If given a pre-existing tokio runtime, it _will_ block the executor while it
+	// waits for the future to complete.
+	pub(crate) fn block_on<F: Future>(&self, future: F) -> LoggerResult<F::Output> {
+		if let Some(handle) = &self.tokio_runtime {
+			Ok(handle.block_on(future))
+		} else {
+			Ok(Runtime::new()?.block_on(future))
+		}
+	}
+}
@@ -0,0 +1,34 @@
+//! Finds a CodeCTRL server running on the same machine through the discovery
+//! file that it writes to its data directory on startup.
+
+use std::{fs, net::IpAddr};
+
+use directories::ProjectDirs;
+use serde::Deserialize;
+
+// Mirrors the `DiscoveryFile` written by `codectrl_server::run_server`. Only
+// the fields needed to connect are read.
+#[derive(Debug, Deserialize)]
+struct DiscoveryFile {
+	host: String,
+	port: u16,
+}
+
+/// Returns the host and port of the running server, if a discovery file was
+/// found.
+pub(crate) fn discover() -> Option<(String, u16)> {
+	let data_dir = ProjectDirs::from("com", "stboyden", "codectrl-server")?
+		.data_dir()
+		.to_owned();
+
+	let contents = fs::read_to_string(data_dir.join("server.json")).ok()?;
+	let DiscoveryFile { host, port } = serde_json::from_str(&contents).ok()?;
+
+	// A server listening on all interfaces can be reached through loopback.
+	let host = match host.parse::<IpAddr>() {
+		Ok(ip) if ip.is_unspecified() => String::from("127.0.0.1"),
+		_ => host,
+	};
+
+	Some((host, port))
+}
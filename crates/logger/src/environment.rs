@@ -0,0 +1,157 @@
+//! The snapshot of the process's environment that is sent with the first log,
+//! so that a logged bug can be reproduced with the same configuration. See
+//! [`LoggerConfig::environment_snapshot`].
+//!
+//! [`LoggerConfig::environment_snapshot`]: crate::LoggerConfig::environment_snapshot
+
+use std::{
+	collections::BTreeMap,
+	env,
+	ffi::OsStr,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		OnceLock,
+	},
+};
+
+use uuid::Uuid;
+
+use codectrl_protobuf_bindings::extensions::{EnvironmentSnapshot, ExtendedLog};
+
+use crate::{
+	redaction::{self, REDACTED},
+	LoggerConfig,
+};
+
+// Parts of the names of variables and arguments whose values are redacted by
+// default.
+const SECRET_NAMES: &[&str] = &[
+	"AUTH",
+	"COOKIE",
+	"CREDENTIAL",
+	"KEY",
+	"PASSWD",
+	"PASSWORD",
+	"PRIVATE",
+	"SECRET",
+	"SESSION",
+	"TOKEN",
+];
+
+static SESSION_ID: OnceLock<String> = OnceLock::new();
+// Only set once the server has confirmed a log carrying the snapshot, so that
+// every log carries it until then.
+static SENT: AtomicBool = AtomicBool::new(false);
+
+/// Identifies this process to the server, which stores its environment
+/// snapshot for every log with the same session ID.
+pub(crate) fn session_id() -> &'static str {
+	SESSION_ID.get_or_init(|| Uuid::new_v4().hyphenated().to_string())
+}
+
+/// Takes a snapshot of the environment if it is enabled in the global
+/// [`LoggerConfig`] and the server hasn't yet confirmed a log carrying one.
+pub(crate) fn snapshot() -> Option<EnvironmentSnapshot> {
+	let config = LoggerConfig::global();
+	let allowlist = config.environment_allowlist.as_ref()?;
+
+	if SENT.load(Ordering::Relaxed) {
+		return None;
+	}
+
+	let redact_secrets = config.redact_secret_environment;
+
+	Some(EnvironmentSnapshot {
+		variables: variables(env::vars_os(), allowlist, redact_secrets),
+		args: args(env::args_os(), redact_secrets),
+		cwd: env::current_dir()
+			.map(|cwd| cwd.display().to_string())
+			.unwrap_or_default(),
+		target: env!("CODECTRL_BUILD_TARGET").to_string(),
+		rustc_version: env!("CODECTRL_BUILD_RUSTC_VERSION").to_string(),
+		profile: env!("CODECTRL_BUILD_PROFILE").to_string(),
+	})
+}
+
+/// To be called once the server has confirmed that it received `log`. Stops
+/// later logs from carrying a snapshot if `log` carried one.
+pub(crate) fn confirm_sent(log: &ExtendedLog) {
+	let carried_snapshot = log
+		.metadata
+		.as_ref()
+		.is_some_and(|metadata| metadata.environment.is_some());
+
+	if carried_snapshot {
+		SENT.store(true, Ordering::Relaxed);
+	}
+}
+
+fn allowed_name(allowed: &str, name: &str) -> bool {
+	match allowed.strip_suffix('*') {
+		Some(prefix) => name.starts_with(prefix),
+		None => name == allowed,
+	}
+}
+
+fn looks_secret(name: &str) -> bool {
+	let name = name.to_ascii_uppercase();
+
+	SECRET_NAMES.iter().any(|secret| name.contains(secret))
+}
+
+// Names and values that aren't valid unicode are converted lossily, as
+// `env::vars` and `env::args` would panic on them.
+pub(crate) fn variables<S: AsRef<OsStr>>(
+	variables: impl Iterator<Item = (S, S)>,
+	allowlist: &[String],
+	redact_secrets: bool,
+) -> BTreeMap<String, String> {
+	variables
+		.map(|(name, value)| {
+			(
+				name.as_ref().to_string_lossy().into_owned(),
+				value.as_ref().to_string_lossy().into_owned(),
+			)
+		})
+		.filter(|(name, _)| allowlist.iter().any(|allowed| allowed_name(allowed, name)))
+		.map(|(name, mut value)| {
+			if redact_secrets && looks_secret(&name) {
+				value = REDACTED.to_string();
+			}
+
+			redaction::redact_text(&mut value);
+			(name, value)
+		})
+		.collect()
+}
+
+// Redacts the values of secret-looking flags, given as either `--token=value`
+// or `--token value`.
+pub(crate) fn args<S: AsRef<OsStr>>(
+	args: impl Iterator<Item = S>,
+	redact_secrets: bool,
+) -> Vec<String> {
+	let mut redact_next = false;
+
+	args
+		.map(|arg| {
+			let mut arg = arg.as_ref().to_string_lossy().into_owned();
+			let is_flag = arg.starts_with('-');
+
+			if redact_secrets {
+				if redact_next && !is_flag {
+					arg = REDACTED.to_string();
+				} else if let Some((flag, _)) = arg.split_once('=').filter(|_| is_flag) {
+					if looks_secret(flag) {
+						arg = format!("{flag}={REDACTED}");
+					}
+				}
+			}
+
+			redact_next = is_flag && !arg.contains('=') && looks_secret(&arg);
+			redaction::redact_text(&mut arg);
+
+			arg
+		})
+		.collect()
+}
@@ -0,0 +1,121 @@
+//! Fingerprints the source that a log was created from, so that a front-end
+//! can tell whether the code snippet it shows still matches the code that
+//! actually ran.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	env, fs,
+	path::Path,
+	sync::{Mutex, OnceLock},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use sha2::{Digest, Sha256};
+
+use codectrl_protobuf_bindings::data::Log;
+
+static BUILD_TIME: OnceLock<Option<u64>> = OnceLock::new();
+// Files are only read and hashed again once their modification time or length
+// changes, as the same few files are fingerprinted for every log in a loop.
+static FILE_HASHES: OnceLock<Mutex<HashMap<String, CachedHash>>> = OnceLock::new();
+
+struct CachedHash {
+	modified: Option<SystemTime>,
+	len: u64,
+	hash: String,
+}
+
+/// Hashes `contents` with SHA-256 and returns it hex encoded.
+pub(crate) fn hash(contents: impl AsRef<[u8]>) -> String {
+	format!("{:x}", Sha256::digest(contents))
+}
+
+/// Hashes the lines of a code snippet, joined by newlines.
+pub(crate) fn hash_snippet(snippet: &BTreeMap<u32, String>) -> String {
+	hash(snippet.values().cloned().collect::<Vec<_>>().join("\n"))
+}
+
+/// Hashes the contents of the logged file and of every file in the stack of
+/// `log`, keyed by their file path. Files that can no longer be read are
+/// skipped.
+pub(crate) fn file_hashes(log: &Log) -> BTreeMap<String, String> {
+	let mut hashes = BTreeMap::new();
+
+	let paths = log
+		.stack
+		.iter()
+		.map(|frame| &frame.file_path)
+		.chain([&log.file_name]);
+
+	for path in paths {
+		if hashes.contains_key(path) {
+			continue;
+		}
+
+		if let Some(hash) = file_hash(path) {
+			hashes.insert(path.clone(), hash);
+		}
+	}
+
+	hashes
+}
+
+fn file_hash(path: &str) -> Option<String> {
+	let metadata = fs::metadata(path).ok()?;
+	let modified = metadata.modified().ok();
+	let len = metadata.len();
+
+	let mut cache = FILE_HASHES
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+	if let Some(cached) = cache.get(path) {
+		if modified.is_some() && cached.modified == modified && cached.len == len {
+			return Some(cached.hash.clone());
+		}
+	}
+
+	let hash = hash(fs::read(path).ok()?);
+
+	cache.insert(
+		path.to_string(),
+		CachedHash {
+			modified,
+			len,
+			hash: hash.clone(),
+		},
+	);
+
+	Some(hash)
+}
+
+/// The time the running binary was built at, as seconds since the Unix epoch.
+///
+/// This is taken from the modification time of the current executable, which
+/// is updated by the linker whenever the binary is rebuilt.
+pub(crate) fn build_time() -> Option<u64> {
+	*BUILD_TIME.get_or_init(|| {
+		let modified = env::current_exe().ok()?.metadata().ok()?.modified().ok()?;
+
+		unix_seconds(modified)
+	})
+}
+
+/// Whether `file_path` was modified after the running binary was built, in
+/// which case the snippet read from it may not match the code that ran.
+pub(crate) fn modified_after_build(file_path: &str) -> bool {
+	let modified = fs::metadata(Path::new(file_path))
+		.and_then(|metadata| metadata.modified())
+		.ok()
+		.and_then(unix_seconds);
+
+	matches!((modified, build_time()), (Some(modified), Some(build_time)) if modified > build_time)
+}
+
+fn unix_seconds(time: SystemTime) -> Option<u64> {
+	time
+		.duration_since(UNIX_EPOCH)
+		.ok()
+		.map(|duration| duration.as_secs())
+}
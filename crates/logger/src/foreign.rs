@@ -0,0 +1,129 @@
+//! Logs created by code written in other languages, such as through the C
+//! bindings in `codectrl-logger-ffi`. As their location can't be found from a
+//! Rust backtrace, it is given by the caller instead.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use uuid::Uuid;
+
+use codectrl_protobuf_bindings::data::{BacktraceData, Log};
+
+use crate::{finish_log, Logger, LoggerConfig, LoggerResult, Warning};
+
+/// A log of a message from another language, created at a location given by
+/// the caller.
+///
+/// The code snippet is read from the file at the location, as for Rust logs,
+/// unless one is given with [`ForeignLog::snippet`].
+///
+/// ```no_run
+/// use codectrl_logger::foreign::ForeignLog;
+///
+/// ForeignLog::new("C", "Connection refused")
+/// 	.location("src/client.c", 42)
+/// 	.function("connect_to_server")
+/// 	.send()
+/// 	.unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ForeignLog {
+	language: String,
+	message: String,
+	file_path: String,
+	line_number: u32,
+	function: String,
+	snippet: BTreeMap<u32, String>,
+}
+
+impl ForeignLog {
+	/// Creates a log of `message` from code written in `language`.
+	pub fn new(language: impl Into<String>, message: impl Into<String>) -> Self {
+		Self {
+			language: language.into(),
+			message: message.into(),
+			file_path: String::new(),
+			line_number: 0,
+			function: String::new(),
+			snippet: BTreeMap::new(),
+		}
+	}
+
+	/// Sets the file and line that the log was created at. Relative paths are
+	/// resolved from the current directory.
+	pub fn location(mut self, file_path: impl Into<String>, line_number: u32) -> Self {
+		self.file_path = file_path.into();
+		self.line_number = line_number;
+		self
+	}
+
+	/// Sets the name of the function that the log was created in.
+	pub fn function(mut self, function: impl Into<String>) -> Self {
+		self.function = function.into();
+		self
+	}
+
+	/// Sets the code snippet to `source`, whose first line is `first_line`,
+	/// rather than reading it from the file.
+	pub fn snippet(mut self, first_line: u32, source: &str) -> Self {
+		self.snippet = (first_line..)
+			.zip(source.lines().map(String::from))
+			.collect();
+		self
+	}
+
+	/// Sends the log using the global [`LoggerConfig`].
+	pub fn send(self) -> LoggerResult<()> { self.send_with_config(LoggerConfig::global()) }
+
+	/// Sends the log using the settings in `config` rather than the global
+	/// [`LoggerConfig`].
+	pub fn send_with_config(self, config: &LoggerConfig) -> LoggerResult<()> {
+		if !Logger::is_enabled() {
+			return Ok(());
+		}
+
+		let file_path = fs::canonicalize(&self.file_path)
+			.ok()
+			.and_then(|path| path.to_str().map(String::from))
+			.unwrap_or(self.file_path);
+		let found = Path::new(&file_path).is_file();
+
+		let code = match self.snippet.get(&self.line_number) {
+			Some(line) => line.trim().to_string(),
+			None if found => Logger::get_code(&file_path, self.line_number),
+			None => String::new(),
+		};
+
+		let mut log = Log {
+			uuid: Uuid::new_v4().hyphenated().to_string(),
+			stack: Vec::new(),
+			line_number: 0,
+			file_name: String::new(),
+			code_snippet: self.snippet,
+			message: self.message,
+			message_type: String::from("string"),
+			address: String::new(),
+			warnings: Vec::new(),
+			language: self.language,
+		};
+
+		if !file_path.is_empty() {
+			if !found && log.code_snippet.is_empty() {
+				log.warnings.push(Warning::SourceNotFound.to_string());
+			}
+
+			log.stack.push(BacktraceData {
+				name: self.function,
+				file_path,
+				line_number: self.line_number,
+				column_number: 0,
+				code,
+			});
+		}
+
+		let Some(log) = finish_log(log, config.surround, config.sampling.as_ref(), "", None) else {
+			return Ok(());
+		};
+
+		config.block_on(Logger::_log(&log, config))?
+	}
+}
@@ -0,0 +1,253 @@
+//! Captures the state of the git repository that a logged source file belongs
+//! to, so that logs from different branches or builds can be told apart.
+//!
+//! The repository is read at runtime from the `.git` directory next to the
+//! source file, and read again at most every few seconds, so a change to the
+//! working tree is reflected in the dirty flag shortly after it is made. To
+//! also record the commit the binary was _built_ from, call
+//! [`emit_build_metadata`] from your build script and
+//! [`register_git_metadata!`] once at startup:
+//!
+//! ```no_run
+//! // build.rs
+//! fn main() { codectrl_logger::git::emit_build_metadata(); }
+//! ```
+//!
+//! ```no_run
+//! // main.rs
+//! fn main() {
+//! 	codectrl_logger::register_git_metadata!();
+//! }
+//! ```
+//!
+//! [`register_git_metadata!`]: crate::register_git_metadata
+
+use std::{
+	collections::HashMap,
+	env, fs,
+	path::{Path, PathBuf},
+	process::Command,
+	sync::{Mutex, OnceLock},
+	time::{Duration, Instant},
+};
+
+pub use codectrl_protobuf_bindings::extensions::GitMetadata;
+
+#[derive(Debug, Clone)]
+struct BuildMetadata {
+	commit: String,
+	branch: String,
+	dirty: bool,
+}
+
+static BUILD_METADATA: OnceLock<BuildMetadata> = OnceLock::new();
+// Reading a repository means spawning `git status`, so the result is cached for
+// each repository and only read again once it is older than `REFRESH_INTERVAL`.
+// The dirty flag of a log can therefore lag behind the working tree by up to
+// that long.
+static REPOSITORIES: OnceLock<Mutex<HashMap<PathBuf, CachedRepository>>> = OnceLock::new();
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+struct CachedRepository {
+	metadata: Option<GitMetadata>,
+	read_at: Instant,
+}
+
+/// Registers the git metadata captured by [`emit_build_metadata`] in the
+/// calling crate's build script. Expands to a call to
+/// [`register_build_metadata`].
+#[macro_export]
+macro_rules! register_git_metadata {
+	() => {
+		$crate::git::register_build_metadata(
+			option_env!("CODECTRL_GIT_COMMIT"),
+			option_env!("CODECTRL_GIT_BRANCH"),
+			option_env!("CODECTRL_GIT_DIRTY"),
+		)
+	};
+}
+
+/// Records the commit, branch and dirty flag that the running binary was built
+/// from. Prefer [`register_git_metadata!`] over calling this directly.
+///
+/// Only the first call has any effect.
+///
+/// [`register_git_metadata!`]: crate::register_git_metadata
+pub fn register_build_metadata(
+	commit: Option<&'static str>,
+	branch: Option<&'static str>,
+	dirty: Option<&'static str>,
+) {
+	if cfg!(feature = "disabled") {
+		return;
+	}
+
+	if let Some(commit) = commit {
+		BUILD_METADATA.get_or_init(|| BuildMetadata {
+			commit: commit.to_string(),
+			branch: branch.unwrap_or_default().to_string(),
+			dirty: dirty == Some("true"),
+		});
+	}
+}
+
+/// To be called from a build script. Reads the repository containing the crate
+/// being built and exposes it to [`register_git_metadata!`] through the
+/// `CODECTRL_GIT_COMMIT`, `CODECTRL_GIT_BRANCH` and `CODECTRL_GIT_DIRTY`
+/// environment variables.
+///
+/// [`register_git_metadata!`]: crate::register_git_metadata
+pub fn emit_build_metadata() {
+	let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") else {
+		return;
+	};
+
+	let Some(repository) = Repository::discover(Path::new(&manifest_dir)) else {
+		return;
+	};
+
+	println!(
+		"cargo:rerun-if-changed={}",
+		repository.git_dir.join("HEAD").display()
+	);
+	println!(
+		"cargo:rerun-if-changed={}",
+		repository.git_dir.join("index").display()
+	);
+
+	if let Some(metadata) = repository.read() {
+		println!("cargo:rustc-env=CODECTRL_GIT_COMMIT={}", metadata.commit);
+		println!("cargo:rustc-env=CODECTRL_GIT_BRANCH={}", metadata.branch);
+		println!("cargo:rustc-env=CODECTRL_GIT_DIRTY={}", metadata.dirty);
+	}
+}
+
+/// Returns the git metadata for the repository containing `file_path`,
+/// combined with the build-time metadata if it was registered.
+pub(crate) fn metadata(file_path: &str) -> Option<GitMetadata> {
+	let build = BUILD_METADATA.get();
+
+	let runtime = Repository::discover(Path::new(file_path)).and_then(|repository| {
+		let mut repositories = REPOSITORIES
+			.get_or_init(Default::default)
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		let cached = repositories
+			.entry(repository.work_tree.clone())
+			.or_insert_with(|| CachedRepository {
+				metadata: repository.read(),
+				read_at: Instant::now(),
+			});
+
+		if cached.read_at.elapsed() >= REFRESH_INTERVAL {
+			cached.metadata = repository.read();
+			cached.read_at = Instant::now();
+		}
+
+		cached.metadata.clone()
+	});
+
+	match (runtime, build) {
+		(Some(mut metadata), build) => {
+			metadata.build_commit = build.map(|build| build.commit.clone());
+			metadata.build_dirty = build.map(|build| build.dirty);
+
+			Some(metadata)
+		},
+		// The source isn't available at runtime (i.e. the binary was deployed
+		// elsewhere), so all we know is what it was built from.
+		(None, Some(build)) => Some(GitMetadata {
+			commit: build.commit.clone(),
+			branch: build.branch.clone(),
+			dirty: build.dirty,
+			build_commit: Some(build.commit.clone()),
+			build_dirty: Some(build.dirty),
+		}),
+		(None, None) => None,
+	}
+}
+
+struct Repository {
+	work_tree: PathBuf,
+	git_dir: PathBuf,
+}
+
+impl Repository {
+	fn discover(path: &Path) -> Option<Self> {
+		path.ancestors().find_map(|directory| {
+			let dot_git = directory.join(".git");
+
+			let git_dir = if dot_git.is_dir() {
+				dot_git
+			} else if dot_git.is_file() {
+				// Worktrees and submodules use a `.git` file pointing at the real git
+				// directory.
+				let contents = fs::read_to_string(&dot_git).ok()?;
+				let git_dir = contents.trim().strip_prefix("gitdir: ")?;
+
+				directory.join(git_dir)
+			} else {
+				return None;
+			};
+
+			Some(Self {
+				work_tree: directory.to_path_buf(),
+				git_dir,
+			})
+		})
+	}
+
+	fn read(&self) -> Option<GitMetadata> {
+		let head = fs::read_to_string(self.git_dir.join("HEAD")).ok()?;
+		let head = head.trim();
+
+		let (commit, branch) = if let Some(reference) = head.strip_prefix("ref: ") {
+			let branch = reference.strip_prefix("refs/heads/").unwrap_or(reference);
+
+			(self.resolve_reference(reference)?, branch.to_string())
+		} else {
+			(head.to_string(), String::from("HEAD"))
+		};
+
+		Some(GitMetadata {
+			commit,
+			branch,
+			dirty: self.is_dirty(),
+			build_commit: None,
+			build_dirty: None,
+		})
+	}
+
+	fn resolve_reference(&self, reference: &str) -> Option<String> {
+		// Worktrees keep their branches in the main repository's git directory.
+		let common_dir = fs::read_to_string(self.git_dir.join("commondir"))
+			.map(|common_dir| self.git_dir.join(common_dir.trim()))
+			.unwrap_or_else(|_| self.git_dir.clone());
+
+		if let Ok(commit) = fs::read_to_string(common_dir.join(reference)) {
+			return Some(commit.trim().to_string());
+		}
+
+		fs::read_to_string(common_dir.join("packed-refs"))
+			.ok()?
+			.lines()
+			.filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+			.filter_map(|line| line.split_once(' '))
+			.find(|(_, name)| *name == reference)
+			.map(|(commit, _)| commit.to_string())
+	}
+
+	// Working out whether the working tree is dirty means comparing it against
+	// the index, which is better left to git itself. If git isn't installed the
+	// repository is assumed to be clean.
+	fn is_dirty(&self) -> bool {
+		Command::new("git")
+			.arg("-C")
+			.arg(&self.work_tree)
+			.args(["status", "--porcelain", "--untracked-files=no"])
+			.output()
+			.map(|output| output.status.success() && !output.stdout.is_empty())
+			.unwrap_or_default()
+	}
+}
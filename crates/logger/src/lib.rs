@@ -1,4 +1,6 @@
 #![doc = include_str!("../README.md")]
+// rustfmt indents the code in doc comments with hard tabs.
+#![allow(clippy::tabs_in_doc_comments)]
 
 use std::{
 	cell::RefCell,
@@ -8,22 +10,200 @@ use std::{
 	fs,
 	fs::File,
 	io::{self, prelude::*, BufReader},
+	path::Path,
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		OnceLock,
+	},
 };
 
 use backtrace::Backtrace;
-use futures_util::stream;
 use hashbag::HashBag;
+use prost::Message;
 use serde::{Deserialize, Serialize};
-use tokio::runtime::{Handle, Runtime};
-use tonic::Request;
+use tokio::runtime::Handle;
+use tonic::{transport::Channel, Code, Request, Status};
+use uuid::Uuid;
+
+use binary::HexDump;
+use log_points::Control;
+use sampling::Admission;
 
 use codectrl_protobuf_bindings::{
 	data::{BacktraceData, Log},
+	extensions::{BreakpointMetadata, ExtendedLog, ExtendedLoggerClient, LogId, LogMetadata},
 	logs_service::{LoggerClient, RequestResult, RequestStatus},
 };
 
+#[doc(hidden)]
+pub mod assertion;
+mod binary;
+pub mod call;
+mod config;
+mod discovery;
+mod environment;
+mod fingerprint;
+pub mod foreign;
+pub mod git;
+mod log_points;
+pub mod redaction;
+mod sampling;
+#[cfg(feature = "semantic-snippets")]
+mod semantic;
+mod stream;
+pub mod test_case;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 #[cfg(test)]
 mod tests;
+mod transport;
+
+pub use codectrl_logger_macros::{instrument, test};
+pub use config::LoggerConfig;
+pub use sampling::Sampling;
+pub use stream::{LogStream, RejectedLog, StreamReport};
+pub use transport::TlsConfig;
+
+/// Sends a log of `message` using the global [`LoggerConfig`].
+///
+/// Any of the [`Sampling`] setters can be given after the message to limit how
+/// many logs are sent from this invocation, overriding the configured
+/// [`Sampling`]. Expands to a call to [`Logger::log_message`] or
+/// [`Logger::log_sampled`].
+///
+/// ```no_run
+/// use codectrl_logger::log;
+///
+/// for i in 0..1_000_000 {
+/// 	log!(i, rate_limit = 10, one_in = 100).unwrap();
+/// }
+/// ```
+#[macro_export]
+macro_rules! log {
+	($message:expr $(,)?) => {
+		$crate::Logger::log_message($message)
+	};
+	($message:expr, $($setting:ident = $value:expr),+ $(,)?) => {
+		$crate::Logger::log_sampled($message, $crate::Sampling::new()$(.$setting($value))+)
+	};
+}
+
+/// Asserts that a boolean expression is `true`, like [`std::assert!`], but
+/// sends a log of the failed expression to CodeCTRL before panicking.
+///
+/// ```no_run
+/// let items: Vec<u32> = vec![];
+///
+/// codectrl_logger::assert!(!items.is_empty(), "no items were loaded");
+/// ```
+#[macro_export]
+macro_rules! assert {
+	($condition:expr $(,)?) => {
+		if !$condition {
+			$crate::assertion::failed(::core::stringify!($condition), ::core::option::Option::None)
+		}
+	};
+	($condition:expr, $($arg:tt)+) => {
+		if !$condition {
+			$crate::assertion::failed(
+				::core::stringify!($condition),
+				::core::option::Option::Some(::core::format_args!($($arg)+)),
+			)
+		}
+	};
+}
+
+/// Asserts that two expressions are equal, like [`std::assert_eq!`], but
+/// sends a log to CodeCTRL before panicking. The log contains the [`Debug`]
+/// output of both values and a diff between them.
+///
+/// [`Debug`]: std::fmt::Debug
+#[macro_export]
+macro_rules! assert_eq {
+	($left:expr, $right:expr $(,)?) => {
+		$crate::__assert_compare!(Eq, $left, $right, ::core::option::Option::None)
+	};
+	($left:expr, $right:expr, $($arg:tt)+) => {
+		$crate::__assert_compare!(
+			Eq,
+			$left,
+			$right,
+			::core::option::Option::Some(::core::format_args!($($arg)+))
+		)
+	};
+}
+
+/// Asserts that two expressions are not equal, like [`std::assert_ne!`], but
+/// sends a log to CodeCTRL before panicking.
+#[macro_export]
+macro_rules! assert_ne {
+	($left:expr, $right:expr $(,)?) => {
+		$crate::__assert_compare!(Ne, $left, $right, ::core::option::Option::None)
+	};
+	($left:expr, $right:expr, $($arg:tt)+) => {
+		$crate::__assert_compare!(
+			Ne,
+			$left,
+			$right,
+			::core::option::Option::Some(::core::format_args!($($arg)+))
+		)
+	};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_compare {
+	($comparison:ident, $left:expr, $right:expr, $message:expr) => {
+		match (&$left, &$right) {
+			(left, right) => {
+				let comparison = $crate::assertion::Comparison::$comparison;
+
+				if comparison.failed(*left == *right) {
+					$crate::assertion::compare_failed(
+						comparison,
+						(::core::stringify!($left), ::core::stringify!($right)),
+						&*left,
+						&*right,
+						$message,
+					)
+				}
+			},
+		}
+	};
+}
+
+/// [`assert!`] that is only checked in builds with debug assertions enabled,
+/// like [`std::debug_assert!`].
+#[macro_export]
+macro_rules! debug_assert {
+	($($arg:tt)*) => {
+		if ::core::cfg!(debug_assertions) {
+			$crate::assert!($($arg)*);
+		}
+	};
+}
+
+/// [`assert_eq!`] that is only checked in builds with debug assertions
+/// enabled, like [`std::debug_assert_eq!`].
+#[macro_export]
+macro_rules! debug_assert_eq {
+	($($arg:tt)*) => {
+		if ::core::cfg!(debug_assertions) {
+			$crate::assert_eq!($($arg)*);
+		}
+	};
+}
+
+/// [`assert_ne!`] that is only checked in builds with debug assertions
+/// enabled, like [`std::debug_assert_ne!`].
+#[macro_export]
+macro_rules! debug_assert_ne {
+	($($arg:tt)*) => {
+		if ::core::cfg!(debug_assertions) {
+			$crate::assert_ne!($($arg)*);
+		}
+	};
+}
 
 /// The Error type used by [`Logger`] and [`LogBatch`] whenever something can
 /// potentially fail.
@@ -46,6 +226,17 @@ pub enum LoggerError {
 		message: String,
 		status_code: String,
 	},
+	/// The server's certificate could not be verified, or the TLS
+	/// configuration given to [`LogBatch::tls`] is invalid.
+	#[error("TLS error: {0}")]
+	TlsError(String),
+	/// The server requires authentication and either no token was given, or
+	/// the given token was rejected. See [`LogBatch::token`].
+	#[error("Authentication with the server failed: {0}")]
+	AuthenticationError(String),
+	/// A pattern given to [`redaction::add_pattern`] is not a valid regex.
+	#[error("Invalid redaction pattern: {0}")]
+	InvalidRedactionPattern(#[from] regex::Error),
 	/// An error generated by either [`Logger`] or [`LogBatch`].
 	#[error("This logger encountered an error: {0}")]
 	LoggerError(String),
@@ -56,6 +247,12 @@ pub enum LoggerError {
 
 impl From<RequestResult> for LoggerError {
 	fn from(res: RequestResult) -> Self {
+		// A successful authentication status can accompany an unrelated error, so
+		// only a failed verification is reported as an authentication error.
+		if let Some(auth_status) = res.auth_status.filter(|auth_status| !auth_status.result) {
+			return Self::AuthenticationError(format!("{}: {auth_status:?}", res.message));
+		}
+
 		Self::LogServerError {
 			message: res.message,
 			status_code: format!("{:?}", res.status),
@@ -65,10 +262,18 @@ impl From<RequestResult> for LoggerError {
 
 type LoggerResult<T> = Result<T, LoggerError>;
 
+const TOKEN_METADATA_KEY: &str = "authorization";
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+static DISABLED_BY_ENV: OnceLock<bool> = OnceLock::new();
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 enum Warning {
 	CompiledWithoutDebugInfo,
 	NoColumnNumberWindows,
+	SourceModifiedAfterBuild,
+	LogsSuppressed(u64),
+	SourceNotFound,
 }
 
 impl ToString for Warning {
@@ -78,21 +283,34 @@ impl ToString for Warning {
 				"File was compiled without debug info, meaning information was lost",
 			Self::NoColumnNumberWindows =>
 				"File was compiled on Windows, which does not always report column numbers",
+			Self::SourceModifiedAfterBuild =>
+				"File was modified after the binary was built, so the code snippet may not match the code \
+				 that ran",
+			Self::SourceNotFound => "Source file could not be found, so there is no code snippet",
+			Self::LogsSuppressed(amount) =>
+				return format!(
+					"{amount} logs from this call site were suppressed by sampling since the last one was \
+					 sent"
+				),
 		}
 		.into()
 	}
 }
 
+// Returns `None` if the log was suppressed by `sampling`.
 fn create_log<T: Debug>(
 	message: T,
 	surround: Option<u32>,
+	sampling: Option<&Sampling>,
 	function_name: Option<&str>,
 	function_name_occurrences: Option<&HashBag<&'static str>>,
-) -> Log {
+) -> Option<ExtendedLog> {
 	let function_name = function_name.unwrap_or_default();
 
 	let mut log = Log {
-		uuid: "".to_string(),
+		// Generated here rather than by the server so that a log that is resent
+		// after a timeout can be recognised as the same log.
+		uuid: Uuid::new_v4().hyphenated().to_string(),
 		stack: Vec::new(),
 		line_number: 0,
 		file_name: String::new(),
@@ -122,68 +340,187 @@ fn create_log<T: Debug>(
 		.warnings
 		.push(Warning::NoColumnNumberWindows.to_string());
 
-	let surround = surround.unwrap_or(3);
-
 	Logger::get_stack_trace(&mut log);
 
+	finish_log(
+		log,
+		surround.unwrap_or(3),
+		sampling,
+		function_name,
+		function_name_occurrences,
+	)
+}
+
+// Applies sampling to a log whose stack has been filled in, then adds its code
+// snippet and metadata. Returns `None` if the log was suppressed by `sampling`.
+fn finish_log(
+	mut log: Log,
+	surround: u32,
+	sampling: Option<&Sampling>,
+	function_name: &str,
+	function_name_occurrences: Option<&HashBag<&'static str>>,
+) -> Option<ExtendedLog> {
+	// Rules set from the server take precedence over the configured sampling.
+	let sampling = match log.stack.last().map(log_points::control) {
+		Some(Control::Suppress) => return None,
+		Some(Control::Sample(sampling)) => Some(sampling),
+		Some(Control::Send) | None => sampling.copied(),
+	};
+
+	if let (Some(last), Some(sampling)) = (log.stack.last(), sampling) {
+		match sampling::admit(last, &sampling) {
+			Admission::Suppress => return None,
+			Admission::Send { suppressed: 0 } => {},
+			Admission::Send { suppressed } => log
+				.warnings
+				.push(Warning::LogsSuppressed(suppressed).to_string()),
+		}
+	}
+
 	if let Some(last) = log.stack.last() {
 		log.line_number = last.line_number;
 
-		log.code_snippet = Logger::get_code_snippet(
-			&last.file_path,
-			&mut log.line_number,
-			surround,
-			function_name,
-			function_name_occurrences,
-		);
+		// Logs from other languages may come with a snippet of their own, or from a
+		// file that can't be found.
+		if log.code_snippet.is_empty() && Path::new(&last.file_path).is_file() {
+			log.code_snippet = Logger::get_code_snippet(
+				&last.file_path,
+				&mut log.line_number,
+				surround,
+				function_name,
+				function_name_occurrences,
+			);
+		}
 
 		log.file_name = last.file_path.clone();
+
+		if fingerprint::modified_after_build(&log.file_name) {
+			log
+				.warnings
+				.push(Warning::SourceModifiedAfterBuild.to_string());
+		}
 	}
 
-	log
+	redaction::redact(&mut log);
+
+	let metadata = LogMetadata {
+		git: git::metadata(&log.file_name),
+		file_hashes: fingerprint::file_hashes(&log),
+		snippet_hash: fingerprint::hash_snippet(&log.code_snippet),
+		build_time: fingerprint::build_time(),
+		call: None,
+		test: test_case::current(),
+		breakpoint: None,
+		binary: None,
+		session_id: environment::session_id().to_string(),
+		environment: environment::snapshot(),
+		pinned: false,
+	};
+
+	Some(ExtendedLog {
+		log: Some(log),
+		metadata: Some(metadata),
+	})
+}
+
+// Attaches the API token, if there is one, to the request as a bearer token.
+fn authorised_request<T>(message: T, token: Option<&str>) -> LoggerResult<Request<T>> {
+	let mut request = Request::new(message);
+
+	if let Some(token) = token {
+		let value = format!("Bearer {token}").parse().map_err(|_| {
+			LoggerError::AuthenticationError("Token contains invalid characters".to_string())
+		})?;
+
+		request.metadata_mut().insert(TOKEN_METADATA_KEY, value);
+	}
+
+	Ok(request)
+}
+
+fn status_to_error(status: Status) -> LoggerError {
+	match status.code() {
+		Code::Unauthenticated | Code::PermissionDenied =>
+			LoggerError::AuthenticationError(status.message().to_string()),
+		_ => status.into(),
+	}
+}
+
+fn check_request_result(result: RequestResult) -> LoggerResult<()> {
+	match result {
+		RequestResult { status, .. } if status == i32::from(RequestStatus::Confirmed) => Ok(()),
+		RequestResult {
+			message,
+			status,
+			auth_status,
+		} if status == i32::from(RequestStatus::Error) => Err(
+			RequestResult {
+				message,
+				status,
+				auth_status,
+			}
+			.into(),
+		),
+		RequestResult { .. } => unreachable!(),
+	}
 }
 
 /// Type used for generating batch logs to be sent by [`Logger`].
-pub struct LogBatch<'a> {
-	logger: Logger<'a>,
-	log_batch: VecDeque<Log>,
-	tokio_runtime: Option<&'a Handle>,
-	host: &'static str,
-	port: &'static str,
-	surround: u32,
+pub struct LogBatch {
+	log_batch: VecDeque<ExtendedLog>,
+	config: LoggerConfig,
 	function_name_occurences: HashBag<&'static str>,
 }
 
-impl<'a> LogBatch<'a> {
-	fn new(logger: Logger<'a>) -> Self {
+impl LogBatch {
+	fn new() -> Self {
 		Self {
-			logger,
 			log_batch: VecDeque::new(),
-			tokio_runtime: None,
-			host: "127.0.0.1",
-			port: "3002",
-			surround: 3,
+			config: LoggerConfig::global().clone(),
 			function_name_occurences: HashBag::new(),
 		}
 	}
 
+	/// Replaces the [`LoggerConfig`] used by this batch, which defaults to
+	/// [`LoggerConfig::global`]. The other setters on [`LogBatch`] modify this
+	/// config.
+	pub fn config(mut self, config: LoggerConfig) -> Self {
+		self.config = config;
+		self
+	}
+
 	/// Sets the host IP address of the gRPC server to connect to.
-	pub fn host(mut self, host: &'static str) -> Self {
-		self.host = host;
+	pub fn host(mut self, host: impl Into<String>) -> Self {
+		self.config = self.config.host(host);
 		self
 	}
 
 	/// Sets the port of the `host` gRPC server to connect to.
-	pub fn port(mut self, port: &'static str) -> Self {
-		self.port = port;
+	pub fn port(mut self, port: impl ToString) -> Self {
+		self.config = self.config.port(port);
+		self
+	}
+
+	/// Sets the API token that is sent to the gRPC server, for servers that
+	/// require authentication. Defaults to the `CODECTRL_TOKEN` environment
+	/// variable, if it is set.
+	pub fn token(mut self, token: impl Into<String>) -> Self {
+		self.config = self.config.token(token);
+		self
+	}
+
+	/// Connects to the gRPC server over TLS using `tls`. TLS is also used,
+	/// with the default [`TlsConfig`], if `host` starts with `https://`.
+	pub fn tls(mut self, tls: TlsConfig) -> Self {
+		self.config = self.config.tls(tls);
 		self
 	}
 
 	/// If a tokio runtime is already present in the parent scope, you can pass
 	/// it here so that a new tokio runtime is not created when the batch is
 	/// sent.
-	pub fn tokio_runtime(mut self, rt: &'a Handle) -> Self {
-		self.tokio_runtime = Some(rt);
+	pub fn tokio_runtime(mut self, rt: &Handle) -> Self {
+		self.config = self.config.tokio_runtime(rt.clone());
 		self
 	}
 
@@ -191,21 +528,36 @@ impl<'a> LogBatch<'a> {
 	/// used where a value isn't manually passed into each `add_X` function,
 	/// otherwise the value passed into those functions will take
 	/// precedence.
+	///
+	/// When the `semantic-snippets` feature is enabled, the surround is only
+	/// used if the enclosing item could not be found or is too long.
 	pub fn surround(mut self, surround: u32) -> Self {
-		self.surround = surround;
+		self.config = self.config.surround(surround);
+		self
+	}
+
+	/// Sets how many logs are sent from each call site in this batch. See
+	/// [`Sampling`].
+	pub fn sampling(mut self, sampling: Sampling) -> Self {
+		self.config = self.config.sampling(sampling);
 		self
 	}
 
 	/// Batch equivalent of [`Logger::log`]. See [`Logger::log`] for relevant
 	/// documentation.
 	pub fn add_log<T: Debug>(mut self, message: T, surround: Option<u32>) -> Self {
-		let surround = Some(surround.unwrap_or(self.surround));
+		if !Logger::is_enabled() {
+			return self;
+		}
+
+		let surround = Some(surround.unwrap_or(self.config.surround));
 
 		self.function_name_occurences.insert("add_log");
 
-		self.log_batch.push_back(create_log(
+		self.log_batch.extend(create_log(
 			message,
 			surround,
+			self.config.sampling.as_ref(),
 			Some("add_log"),
 			Some(&self.function_name_occurences),
 		));
@@ -221,14 +573,19 @@ impl<'a> LogBatch<'a> {
 		message: T,
 		surround: Option<u32>,
 	) -> Self {
-		let surround = Some(surround.unwrap_or(self.surround));
+		if !Logger::is_enabled() {
+			return self;
+		}
+
+		let surround = Some(surround.unwrap_or(self.config.surround));
 
 		self.function_name_occurences.insert("add_log_if");
 
 		if condition() {
-			self.log_batch.push_back(create_log(
+			self.log_batch.extend(create_log(
 				message,
 				surround,
+				self.config.sampling.as_ref(),
 				Some("add_log_if"),
 				Some(&self.function_name_occurences),
 			));
@@ -245,14 +602,19 @@ impl<'a> LogBatch<'a> {
 		message: T,
 		surround: Option<u32>,
 	) -> Self {
-		let surround = Some(surround.unwrap_or(self.surround));
+		if !Logger::is_enabled() {
+			return self;
+		}
+
+		let surround = Some(surround.unwrap_or(self.config.surround));
 
 		self.function_name_occurences.insert("add_boxed_log_if");
 
 		if condition() {
-			self.log_batch.push_back(create_log(
+			self.log_batch.extend(create_log(
 				message,
 				surround,
+				self.config.sampling.as_ref(),
 				Some("add_boxed_log_if"),
 				Some(&self.function_name_occurences),
 			));
@@ -264,14 +626,19 @@ impl<'a> LogBatch<'a> {
 	/// Batch equivalent of [`Logger::log_when_env`]. See
 	/// [`Logger::log_when_env`] for relevant documentation.
 	pub fn add_log_when_env<T: Debug>(mut self, message: T, surround: Option<u32>) -> Self {
-		let surround = Some(surround.unwrap_or(self.surround));
+		if !Logger::is_enabled() {
+			return self;
+		}
+
+		let surround = Some(surround.unwrap_or(self.config.surround));
 
 		self.function_name_occurences.insert("add_log_when_env");
 
 		if env::var("CODECTRL_DEBUG").ok().is_some() {
-			self.log_batch.push_back(create_log(
+			self.log_batch.extend(create_log(
 				message,
 				surround,
+				self.config.sampling.as_ref(),
 				Some("add_log_when_env"),
 				Some(&self.function_name_occurences),
 			));
@@ -287,15 +654,11 @@ impl<'a> LogBatch<'a> {
 	/// multiple [`Log`]s with one gRPC connection.
 	///
 	/// [`Log`]: codectrl_protobuf_bindings::data::Log
-	pub fn build(mut self) -> Logger<'a> {
-		self.logger = Logger {
+	pub fn build(self) -> Logger {
+		Logger {
 			log_batch: self.log_batch,
-			batch_host: self.host,
-			batch_port: self.port,
-			batch_tokio_runtime: self.tokio_runtime,
-		};
-
-		self.logger
+			config: self.config,
+		}
 	}
 }
 
@@ -304,77 +667,137 @@ impl<'a> LogBatch<'a> {
 ///
 /// [`Log`]: codectrl_protobuf_bindings::data::Log
 #[derive(Debug, Clone, Default)]
-pub struct Logger<'a> {
-	log_batch: VecDeque<Log>,
-	batch_host: &'static str,
-	batch_port: &'static str,
-	batch_tokio_runtime: Option<&'a Handle>,
+pub struct Logger {
+	log_batch: VecDeque<ExtendedLog>,
+	config: LoggerConfig,
 }
 
-impl<'a> Logger<'a> {
+impl Logger {
 	/// Returns a [`LogBatch`], which can be used to start the process of
 	/// generating multiple logs to be sent in a single connection. Should
 	/// be preferred over sending one-time [`Log`]s if possible.
 	///
 	/// [`Log`]: codectrl_protobuf_bindings::data::Log
-	pub fn start_batch() -> LogBatch<'a> { LogBatch::new(Self::default()) }
+	pub fn start_batch() -> LogBatch { LogBatch::new() }
 
-	/// Sends the configured batch in `log_batch` to the configured `batch_host`
-	/// and `batch_port`. This _should_ be the preferred way of sending
-	/// multiple logs.
+	/// Returns a [`LogStream`] to the server in the global [`LoggerConfig`],
+	/// which sends logs over one long-running connection as they are created.
+	/// Should be preferred over [`Self::log`] for long-running processes that
+	/// log continuously.
+	pub fn stream() -> LoggerResult<LogStream> { LogStream::new(LoggerConfig::global().clone()) }
+
+	/// Whether logs are currently being created and sent.
+	///
+	/// This is always `false` when this crate is compiled with the `disabled`
+	/// feature, in which case every logging function is optimised away to a
+	/// no-op. Otherwise, logging can be turned off at runtime with
+	/// [`Self::disable`] or by setting the `CODECTRL_DISABLED` environment
+	/// variable.
+	#[inline(always)]
+	pub fn is_enabled() -> bool {
+		if cfg!(feature = "disabled") {
+			return false;
+		}
+
+		!DISABLED.load(Ordering::Relaxed)
+			&& !*DISABLED_BY_ENV.get_or_init(|| env::var("CODECTRL_DISABLED").is_ok())
+	}
+
+	/// Turns every [`Logger`] and [`LogBatch`] function into a no-op until
+	/// [`Self::enable`] is called.
+	pub fn disable() { DISABLED.store(true, Ordering::Relaxed); }
+
+	/// Re-enables logging after a call to [`Self::disable`]. Has no effect if
+	/// this crate is compiled with the `disabled` feature or the
+	/// `CODECTRL_DISABLED` environment variable is set.
+	pub fn enable() { DISABLED.store(false, Ordering::Relaxed); }
+
+	/// Sends the configured batch in `log_batch` to the configured host and
+	/// port. This _should_ be the preferred way of sending multiple logs.
 	///
 	/// If given a pre-existing tokio runtime, it _will_ block the executor
 	/// while it waits for the log to complete.
 	pub fn send_batch(&mut self) -> LoggerResult<()> {
+		if !Self::is_enabled() {
+			return Ok(());
+		}
+
 		if self.log_batch.is_empty() {
 			return Err(LoggerError::LoggerError(
 				"Can't send batch: Log batch is empty".to_string(),
 			));
 		}
 
-		let mut ret = Ok(());
-
-		async fn send_batch(host: &str, port: &str, logs: &VecDeque<Log>) -> LoggerResult<()> {
-			let mut log_client = LoggerClient::connect(format!("http://{host}:{port}")).await?;
+		async fn send_batch(config: &LoggerConfig, logs: &VecDeque<ExtendedLog>) -> LoggerResult<()> {
+			let channel = transport::connect(config).await?;
+			let encoded_len = logs.iter().map(Message::encoded_len).sum();
+			let compress = transport::should_compress(config, encoded_len);
+
+			match send(config, channel.clone(), logs, compress).await {
+				// The server doesn't accept compressed logs.
+				Err(LoggerError::TonicStatusCode(status))
+					if compress && transport::compression_rejected(&status) =>
+					send(config, channel, logs, false).await,
+				result => result,
+			}
+		}
 
-			let request = Request::new(stream::iter(logs.clone()));
-			let response = log_client.send_logs(request).await?;
+		async fn send(
+			config: &LoggerConfig,
+			channel: Channel,
+			logs: &VecDeque<ExtendedLog>,
+			compress: bool,
+		) -> LoggerResult<()> {
+			let token = config.token.as_deref();
+			let mut log_client = ExtendedLoggerClient::new(channel.clone());
+
+			if compress {
+				log_client = log_client.send_gzip();
+			}
 
-			match response.into_inner() {
-				RequestResult { status, .. } if status == RequestStatus::Confirmed.into() => Ok(()),
-				RequestResult {
-					message,
-					status,
-					auth_status,
-				} if status == RequestStatus::Error.into() => Err(
-					RequestResult {
-						message,
-						status,
-						auth_status,
+			let request = authorised_request(futures_util::stream::iter(logs.clone()), token)?;
+			let response = match log_client.send_extended_logs(request).await {
+				// The server doesn't know about the extensions service, so only send the
+				// logs themselves.
+				Err(status)
+					if status.code() == Code::Unimplemented && !transport::compression_rejected(&status) =>
+				{
+					let mut log_client = LoggerClient::new(channel);
+
+					if compress {
+						log_client = log_client.send_gzip();
 					}
-					.into(),
-				),
-				RequestResult { .. } => unreachable!(),
-			}
-		}
 
-		if let Some(handle) = self.batch_tokio_runtime {
-			handle.block_on(async {
-				ret = send_batch(self.batch_host, self.batch_port, &self.log_batch).await;
-			});
-		} else {
-			let rt = Runtime::new()?;
+					let logs = logs
+						.iter()
+						.filter_map(|log| log.log.clone())
+						.collect::<Vec<_>>();
+
+					log_client
+						.send_logs(authorised_request(futures_util::stream::iter(logs), token)?)
+						.await
+						.map_err(status_to_error)?
+				},
+				response => response.map_err(status_to_error)?,
+			};
+
+			check_request_result(response.into_inner())?;
+			logs.iter().for_each(environment::confirm_sent);
 
-			rt.block_on(async {
-				ret = send_batch(self.batch_host, self.batch_port, &self.log_batch).await;
-			})
+			Ok(())
 		}
 
-		ret
+		self
+			.config
+			.block_on(send_batch(&self.config, &self.log_batch))?
 	}
 
 	/// The main log function that is called from Rust code.
 	///
+	/// Any of `surround`, `host`, `port` and `tokio_runtime` that are `None`
+	/// are taken from the global [`LoggerConfig`]. TLS is used if `host` starts
+	/// with `https://`.
+	///
 	/// This function will print a warning to stderr if this crate is compiled
 	/// with debug_assertions disabled as it will produce a much less
 	/// informative log for codeCTRL.
@@ -388,26 +811,202 @@ impl<'a> Logger<'a> {
 		port: Option<&str>,
 		tokio_runtime: Option<&Handle>,
 	) -> LoggerResult<()> {
-		let host = host.unwrap_or("127.0.0.1");
-		let port = port.unwrap_or("3002");
+		if !Self::is_enabled() {
+			return Ok(());
+		}
 
-		let log = create_log(message, surround, None, None);
+		let mut config = LoggerConfig::global().clone();
 
-		let mut ret = Ok(());
+		if let Some(surround) = surround {
+			config = config.surround(surround);
+		}
+
+		if let Some(host) = host {
+			config = config.host(host);
+		}
+
+		if let Some(port) = port {
+			config = config.port(port);
+		}
 
 		if let Some(handle) = tokio_runtime {
-			handle.block_on(async {
-				ret = Self::_log(&log, host, port).await;
-			});
-		} else {
-			let rt = Runtime::new()?;
+			config = config.tokio_runtime(handle.clone());
+		}
+
+		Self::log_with_config(message, &config)
+	}
+
+	/// Equivalent to calling [`Self::log`] with every optional parameter set
+	/// to `None`, meaning that the global [`LoggerConfig`] is used.
+	pub fn log_message<T: Debug>(message: T) -> LoggerResult<()> {
+		Self::log_with_config(message, LoggerConfig::global())
+	}
 
-			rt.block_on(async {
-				ret = Self::_log(&log, host, port).await;
-			})
+	/// Sends a log using the settings in `config` rather than the global
+	/// [`LoggerConfig`]. See [`Self::log`] for relevant documentation.
+	pub fn log_with_config<T: Debug>(message: T, config: &LoggerConfig) -> LoggerResult<()> {
+		if !Self::is_enabled() {
+			return Ok(());
 		}
 
-		ret
+		let Some(log) = create_log(
+			message,
+			Some(config.surround),
+			config.sampling.as_ref(),
+			None,
+			None,
+		) else {
+			return Ok(());
+		};
+
+		config.block_on(Self::_log(&log, config))?
+	}
+
+	/// Sends a log using the global [`LoggerConfig`], but with `sampling`
+	/// applied to this call site rather than the configured [`Sampling`].
+	pub fn log_sampled<T: Debug>(message: T, sampling: Sampling) -> LoggerResult<()> {
+		if !Self::is_enabled() {
+			return Ok(());
+		}
+
+		Self::log_with_config(message, &LoggerConfig::global().clone().sampling(sampling))
+	}
+
+	/// Sends a summary log for each call site that has had logs suppressed by
+	/// [`Sampling`] since the last log sent from it, using the global
+	/// [`LoggerConfig`].
+	///
+	/// Suppressed logs are otherwise only reported as a warning on the next log
+	/// sent from the same call site, so this should be called once a sampled
+	/// loop has finished or before the program exits.
+	pub fn flush_suppressed() -> LoggerResult<()> {
+		Self::flush_suppressed_with_config(LoggerConfig::global())
+	}
+
+	/// Sends the summary logs of [`Self::flush_suppressed`] using the settings
+	/// in `config` rather than the global [`LoggerConfig`].
+	pub fn flush_suppressed_with_config(config: &LoggerConfig) -> LoggerResult<()> {
+		if !Self::is_enabled() {
+			return Ok(());
+		}
+
+		for (frame, suppressed) in sampling::take_suppressed() {
+			let log = Log {
+				uuid: Uuid::new_v4().hyphenated().to_string(),
+				stack: vec![frame],
+				line_number: 0,
+				file_name: String::new(),
+				code_snippet: BTreeMap::new(),
+				message: format!("{suppressed} logs from this call site were suppressed by sampling"),
+				message_type: String::from("string"),
+				address: String::new(),
+				warnings: Vec::new(),
+				language: "Rust".into(),
+			};
+
+			// The summary itself isn't sampled, as that would suppress it again.
+			let Some(log) = finish_log(log, config.surround, None, "", None) else {
+				continue;
+			};
+
+			config.block_on(Self::_log(&log, config))??;
+		}
+
+		Ok(())
+	}
+
+	/// Sends a log of the byte buffer `bytes` using the global
+	/// [`LoggerConfig`]. Rather than the [`Debug`] output of every byte, the
+	/// raw bytes are sent, which the CodeCTRL GUI shows as a hex dump and can
+	/// decode as UTF-8, CBOR or protobuf.
+	///
+	/// ```no_run
+	/// use codectrl_logger::Logger;
+	///
+	/// let frame = [0x0a, 0x05, b'h', b'e', b'l', b'l', b'o'];
+	///
+	/// Logger::log_bytes(frame).unwrap();
+	/// ```
+	pub fn log_bytes(bytes: impl AsRef<[u8]>) -> LoggerResult<()> {
+		Self::log_bytes_with_config(bytes, binary::OCTET_STREAM, LoggerConfig::global())
+	}
+
+	/// Sends a log of `bytes`, whose MIME type is `content_type`, using the
+	/// settings in `config`. The GUI uses the content type to pick how the
+	/// bytes are first shown, such as decoded as CBOR for `application/cbor`.
+	/// See [`Self::log_bytes`].
+	pub fn log_bytes_with_config(
+		bytes: impl AsRef<[u8]>,
+		content_type: &str,
+		config: &LoggerConfig,
+	) -> LoggerResult<()> {
+		if !Self::is_enabled() {
+			return Ok(());
+		}
+
+		// The bytes are redacted up front so that the hex dump in the message is
+		// made from the redacted bytes as well.
+		let bytes = redaction::redact_bytes(bytes.as_ref());
+		let bytes = bytes.as_ref();
+
+		let Some(mut log) = create_log(
+			HexDump(bytes),
+			Some(config.surround),
+			config.sampling.as_ref(),
+			None,
+			None,
+		) else {
+			return Ok(());
+		};
+
+		binary::attach(&mut log, bytes, content_type);
+
+		config.block_on(Self::_log(&log, config))?
+	}
+
+	/// Sends a log of `value` marked as a breakpoint using the global
+	/// [`LoggerConfig`], then blocks the calling thread until the log is
+	/// resumed by pressing "Continue" in the CodeCTRL GUI, or until the
+	/// [`LoggerConfig::breakpoint_timeout`] passes.
+	///
+	/// Returns `true` if the breakpoint was resumed from the GUI, and `false`
+	/// if it timed out or wasn't sent, such as when its call site was disabled
+	/// as a remote log point.
+	///
+	/// ```no_run
+	/// use codectrl_logger::Logger;
+	///
+	/// let retries = 3;
+	///
+	/// Logger::pause_here(retries).unwrap();
+	/// ```
+	pub fn pause_here<T: Debug>(value: T) -> LoggerResult<bool> {
+		Self::pause_here_with_config(value, LoggerConfig::global())
+	}
+
+	/// Pauses at a breakpoint using the settings in `config` rather than the
+	/// global [`LoggerConfig`]. See [`Self::pause_here`] for relevant
+	/// documentation.
+	pub fn pause_here_with_config<T: Debug>(value: T, config: &LoggerConfig) -> LoggerResult<bool> {
+		if !Self::is_enabled() {
+			return Ok(false);
+		}
+
+		// Breakpoints aren't sampled, so that every pass through them pauses.
+		let Some(mut log) = create_log(value, Some(config.surround), None, None, None) else {
+			return Ok(false);
+		};
+
+		if let Some(metadata) = log.metadata.as_mut() {
+			metadata.breakpoint = Some(BreakpointMetadata {
+				timeout_millis: u64::try_from(config.breakpoint_timeout.as_millis()).unwrap_or(u64::MAX),
+			});
+		}
+
+		config.block_on(async {
+			Self::_log(&log, config).await?;
+			Self::wait_for_resume(&log, config).await
+		})?
 	}
 
 	/// A log function that takes a closure and only logs out if that function
@@ -425,6 +1024,10 @@ impl<'a> Logger<'a> {
 		port: Option<&str>,
 		tokio_runtime: Option<&Handle>,
 	) -> LoggerResult<bool> {
+		if !Self::is_enabled() {
+			return Ok(false);
+		}
+
 		if condition() {
 			Self::log(message, surround, host, port, tokio_runtime)?;
 			return Ok(true);
@@ -446,6 +1049,10 @@ impl<'a> Logger<'a> {
 		port: Option<&str>,
 		tokio_runtime: Option<&Handle>,
 	) -> LoggerResult<bool> {
+		if !Self::is_enabled() {
+			return Ok(false);
+		}
+
 		if condition() {
 			Self::log(message, surround, host, port, tokio_runtime)?;
 			return Ok(true);
@@ -467,6 +1074,10 @@ impl<'a> Logger<'a> {
 		port: Option<&str>,
 		tokio_runtime: Option<&Handle>,
 	) -> LoggerResult<bool> {
+		if !Self::is_enabled() {
+			return Ok(false);
+		}
+
 		if env::var("CODECTRL_DEBUG").ok().is_some() {
 			Self::log(message, surround, host, port, tokio_runtime)?;
 			Ok(true)
@@ -483,30 +1094,92 @@ impl<'a> Logger<'a> {
 	//
 	// TODO: Provide a direct wrapper so that async environments do not need to call
 	// a non-async wrapper, just for that to call an async wrapper.
-	async fn _log(log: &Log, host: &str, port: &str) -> LoggerResult<()> {
-		let mut log_client = LoggerClient::connect(format!("http://{host}:{port}")).await?;
+	async fn _log(log: &ExtendedLog, config: &LoggerConfig) -> LoggerResult<()> {
+		let channel = transport::connect(config).await?;
+		let compress = transport::should_compress(config, log.encoded_len());
+
+		match Self::send_log(log, config, channel.clone(), compress).await {
+			// The server doesn't accept compressed logs.
+			Err(LoggerError::TonicStatusCode(status))
+				if compress && transport::compression_rejected(&status) =>
+				Self::send_log(log, config, channel, false).await,
+			result => result,
+		}
+	}
 
-		let request = Request::new(log.clone());
-		let response = log_client.send_log(request).await?;
+	async fn wait_for_resume(log: &ExtendedLog, config: &LoggerConfig) -> LoggerResult<bool> {
+		// The request timeout would otherwise end the wait early.
+		let config = LoggerConfig {
+			timeout: None,
+			..config.clone()
+		};
 
-		match response.into_inner() {
-			RequestResult { status, .. } if status == RequestStatus::Confirmed.into() => Ok(()),
-			RequestResult {
-				message,
-				status,
-				auth_status,
-			} if status == RequestStatus::Error.into() => Err(
-				RequestResult {
-					message,
-					status,
-					auth_status,
-				}
-				.into(),
-			),
-			RequestResult { .. } => unreachable!(),
+		let channel = transport::connect(&config).await?;
+		let mut log_client = ExtendedLoggerClient::new(channel);
+
+		let uuid = log
+			.log
+			.as_ref()
+			.map(|log| log.uuid.clone())
+			.unwrap_or_default();
+		let request = authorised_request(LogId { uuid }, config.token.as_deref())?;
+
+		match tokio::time::timeout(
+			config.breakpoint_timeout,
+			log_client.wait_for_resume(request),
+		)
+		.await
+		{
+			Ok(response) => {
+				check_request_result(response.map_err(status_to_error)?.into_inner())?;
+				Ok(true)
+			},
+			Err(_) => Ok(false),
 		}
 	}
 
+	async fn send_log(
+		log: &ExtendedLog,
+		config: &LoggerConfig,
+		channel: Channel,
+		compress: bool,
+	) -> LoggerResult<()> {
+		let token = config.token.as_deref();
+		let mut log_client = ExtendedLoggerClient::new(channel.clone());
+
+		if compress {
+			log_client = log_client.send_gzip();
+		}
+
+		let request = authorised_request(log.clone(), token)?;
+		let response = match log_client.send_extended_log(request).await {
+			// The server doesn't know about the extensions service, so only send the
+			// log itself.
+			Err(status)
+				if status.code() == Code::Unimplemented && !transport::compression_rejected(&status) =>
+			{
+				let mut log_client = LoggerClient::new(channel);
+
+				if compress {
+					log_client = log_client.send_gzip();
+				}
+
+				let request = authorised_request(log.log.clone().unwrap_or_default(), token)?;
+
+				log_client
+					.send_log(request)
+					.await
+					.map_err(status_to_error)?
+			},
+			response => response.map_err(status_to_error)?,
+		};
+
+		check_request_result(response.into_inner())?;
+		environment::confirm_sent(log);
+
+		Ok(())
+	}
+
 	fn get_stack_trace(log: &mut Log) {
 		let backtrace = Backtrace::new();
 
@@ -548,6 +1221,9 @@ impl<'a> Logger<'a> {
 						|| name == "codectrl_logger"
 						|| name.ends_with("create_log")
 						|| name.contains("codectrl_logger::create_log")
+						|| name.contains("codectrl_logger::assertion")
+						|| name.contains("codectrl_logger::call")
+						|| name.contains("codectrl_logger::stream")
 						|| file_path.contains(".cargo")
 						|| file_path.starts_with("/rustc/"))
 						&& file_path.contains(".rs")
@@ -651,6 +1327,20 @@ impl<'a> Logger<'a> {
 			}
 		}
 
+		// With `semantic-snippets` enabled, prefer sending the entire function or
+		// `impl` block that the log was created in, falling back to the
+		// `surround` window if it can't be found or is too long.
+		#[cfg(feature = "semantic-snippets")]
+		if let Some(range) = fs::read_to_string(file_path)
+			.ok()
+			.and_then(|source| semantic::enclosing_item(&source, *line_number))
+		{
+			return lines
+				.range(range)
+				.map(|(key, value)| (*key, value.clone()))
+				.collect();
+		}
+
 		let offset = line_number.saturating_sub(surround);
 		let end = line_number.saturating_add(surround);
 
@@ -0,0 +1,259 @@
+//! Remote log points, which let the server enable, disable and sample each call
+//! site while the program is running. See
+//! [`LoggerConfig::remote_log_points`].
+//!
+//! Every call site is registered with the server the first time it logs, and
+//! rules for call sites are received over a long-running stream from a
+//! background thread, which reconnects if the server restarts.
+//!
+//! [`LoggerConfig::remote_log_points`]: crate::LoggerConfig::remote_log_points
+
+use std::{
+	collections::HashMap,
+	sync::{Condvar, Mutex, MutexGuard, OnceLock, RwLock},
+	thread,
+	time::Duration,
+};
+
+use tokio::{
+	runtime::Builder,
+	sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+	time::sleep,
+};
+use tonic::{transport::Channel, Code};
+
+use codectrl_protobuf_bindings::{
+	data::BacktraceData,
+	extensions::{CallSite, CallSiteRule, ExtendedLoggerClient},
+};
+
+use crate::{
+	authorised_request, fingerprint, status_to_error, transport, LoggerConfig, LoggerError,
+	LoggerResult, Sampling,
+};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// How long the first log waits for the rules to be received, so that a call
+// site that was disabled in a previous run doesn't send a log before its rule
+// arrives.
+const INITIAL_RULES_TIMEOUT: Duration = Duration::from_millis(500);
+
+static WATCHER: OnceLock<UnboundedSender<CallSite>> = OnceLock::new();
+static RULES: OnceLock<RwLock<HashMap<String, CallSiteRule>>> = OnceLock::new();
+static REGISTERED: OnceLock<Mutex<HashMap<String, CallSite>>> = OnceLock::new();
+static RECEIVED_RULES: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+/// What the server wants done with logs from a call site.
+pub(crate) enum Control {
+	Send,
+	Sample(Sampling),
+	Suppress,
+}
+
+/// Registers the call site of `frame` with the server, if it hasn't been
+/// already, and returns its rule. Every log is sent unless remote log points
+/// are enabled in the global [`LoggerConfig`].
+pub(crate) fn control(frame: &BacktraceData) -> Control {
+	control_with(frame, LoggerConfig::global())
+}
+
+// Rules are watched for using the `config` of the first call, which is always
+// the global config outside of tests.
+pub(crate) fn control_with(frame: &BacktraceData, config: &'static LoggerConfig) -> Control {
+	if !config.remote_log_points {
+		return Control::Send;
+	}
+
+	let call_site = call_site(frame);
+	let id = call_site.id.clone();
+	let watcher = WATCHER.get_or_init(|| start(config));
+
+	if registered().insert(id.clone(), call_site.clone()).is_none() {
+		watcher.send(call_site).ok();
+	}
+
+	wait_for_rules();
+
+	let rules = rules()
+		.read()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+	match rules.get(&id) {
+		None => Control::Send,
+		Some(rule) if !rule.enabled => Control::Suppress,
+		Some(CallSiteRule {
+			rate_limit: None,
+			one_in: None,
+			..
+		}) => Control::Send,
+		Some(rule) => {
+			let mut sampling = Sampling::new();
+
+			if let Some(rate_limit) = rule.rate_limit {
+				sampling = sampling.rate_limit(rate_limit);
+			}
+
+			if let Some(one_in) = rule.one_in {
+				sampling = sampling.one_in(one_in);
+			}
+
+			Control::Sample(sampling)
+		},
+	}
+}
+
+// Call sites are identified by their location, in the same way as for
+// sampling.
+pub(crate) fn call_site(frame: &BacktraceData) -> CallSite {
+	let location = format!(
+		"{}:{}:{}",
+		frame.file_path, frame.line_number, frame.column_number
+	);
+
+	CallSite {
+		id: fingerprint::hash(location)[..16].to_string(),
+		file_path: frame.file_path.clone(),
+		line_number: frame.line_number,
+		column_number: frame.column_number,
+		function: frame.name.clone(),
+	}
+}
+
+fn rules() -> &'static RwLock<HashMap<String, CallSiteRule>> { RULES.get_or_init(Default::default) }
+
+fn registered() -> MutexGuard<'static, HashMap<String, CallSite>> {
+	REGISTERED
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn wait_for_rules() {
+	let (received, changed) = &RECEIVED_RULES;
+	let received = received
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+	changed
+		.wait_timeout_while(received, INITIAL_RULES_TIMEOUT, |received| !*received)
+		.ok();
+}
+
+fn received_rules() {
+	let (received, changed) = &RECEIVED_RULES;
+
+	*received
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner()) = true;
+	changed.notify_all();
+}
+
+// Watches for rules on a thread of its own, so that it keeps running between
+// logs regardless of which runtime they are sent from.
+fn start(config: &'static LoggerConfig) -> UnboundedSender<CallSite> {
+	let (sender, receiver) = mpsc::unbounded_channel();
+
+	let spawned = thread::Builder::new()
+		.name("codectrl-log-points".into())
+		.spawn(move || {
+			match Builder::new_current_thread().enable_all().build() {
+				Ok(runtime) => runtime.block_on(watch(config, receiver)),
+				Err(error) => eprintln!("Could not watch for log point rules: {error}"),
+			}
+
+			// Logs shouldn't wait for rules that won't arrive.
+			received_rules();
+		});
+
+	if spawned.is_err() {
+		received_rules();
+	}
+
+	sender
+}
+
+async fn watch(config: &LoggerConfig, mut receiver: UnboundedReceiver<CallSite>) {
+	loop {
+		match watch_connection(config, &mut receiver).await {
+			Ok(()) => return,
+			Err(LoggerError::TonicStatusCode(status)) if status.code() == Code::Unimplemented => {
+				eprintln!("The CodeCTRL server doesn't support remote log points");
+				return;
+			},
+			// The first log shouldn't wait for the server to come back up.
+			Err(_) => received_rules(),
+		}
+
+		sleep(RECONNECT_DELAY).await;
+	}
+}
+
+async fn watch_connection(
+	config: &LoggerConfig,
+	receiver: &mut UnboundedReceiver<CallSite>,
+) -> LoggerResult<()> {
+	let channel = transport::connect(config).await?;
+	let mut client = ExtendedLoggerClient::new(channel);
+	let token = config.token.as_deref();
+
+	let mut updates = client
+		.watch_call_site_rules(authorised_request((), token)?)
+		.await
+		.map_err(status_to_error)?
+		.into_inner();
+
+	// Every queued call site is also registered, so they are all sent below.
+	while receiver.try_recv().is_ok() {}
+
+	// The server may have restarted since the call sites were registered.
+	let call_sites = registered().values().cloned().collect::<Vec<_>>();
+
+	for call_site in call_sites {
+		register(&mut client, call_site, token).await?;
+	}
+
+	let mut first = true;
+
+	loop {
+		tokio::select! {
+			call_site = receiver.recv() => match call_site {
+				Some(call_site) => register(&mut client, call_site, token).await?,
+				None => return Ok(()),
+			},
+			update = updates.message() => match update.map_err(status_to_error)? {
+				Some(update) => {
+					let mut rules = rules().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+					// The first message contains every rule.
+					if first {
+						rules.clear();
+						first = false;
+					}
+
+					for rule in update.rules {
+						rules.insert(rule.call_site_id.clone(), rule);
+					}
+
+					drop(rules);
+					received_rules();
+				},
+				None => return Err(LoggerError::LoggerError(
+					"The server closed the stream of log point rules".to_string(),
+				)),
+			},
+		}
+	}
+}
+
+async fn register(
+	client: &mut ExtendedLoggerClient<Channel>,
+	call_site: CallSite,
+	token: Option<&str>,
+) -> LoggerResult<()> {
+	client
+		.register_call_site(authorised_request(call_site, token)?)
+		.await
+		.map_err(status_to_error)?;
+
+	Ok(())
+}
@@ -0,0 +1,164 @@
+//! Scrubs secrets from logs before they leave the machine.
+//!
+//! Redactions are registered globally and are applied to every log created by
+//! [`Logger`] and [`LogBatch`], after the log has been fully created but before
+//! it is sent. Regex redactions replace every match in the log's message, code
+//! snippet, the code of each frame in its stack and the bytes logged with
+//! [`Logger::log_bytes`] with [`REDACTED`]. Hooks are given the entire [`Log`]
+//! to modify as they see fit.
+//!
+//! ```no_run
+//! use codectrl_logger::redaction;
+//!
+//! redaction::add_pattern(redaction::EMAIL_PATTERN).unwrap();
+//! redaction::add_pattern(r"sk_live_[A-Za-z0-9]+").unwrap();
+//! redaction::add_hook(|log| log.address.clear());
+//! ```
+//!
+//! [`Logger`]: crate::Logger
+//! [`LogBatch`]: crate::LogBatch
+//! [`Logger::log_bytes`]: crate::Logger::log_bytes
+//! [`Log`]: codectrl_protobuf_bindings::data::Log
+
+use std::{
+	borrow::Cow,
+	sync::{Arc, RwLock},
+};
+
+use regex::{bytes, Regex};
+
+use codectrl_protobuf_bindings::data::Log;
+
+use crate::LoggerResult;
+
+/// The text that matches of a regex redaction are replaced with.
+pub const REDACTED: &str = "<REDACTED>";
+
+/// Matches most email addresses.
+pub const EMAIL_PATTERN: &str = r"[A-Za-z0-9._%+\-]+@[A-Za-z0-9.\-]+\.[A-Za-z]{2,}";
+
+/// Matches bearer tokens, as found in `Authorization` headers.
+pub const BEARER_TOKEN_PATTERN: &str = r"(?i)bearer\s+[A-Za-z0-9\-._~+/]+=*";
+
+type Hook = Arc<dyn Fn(&mut Log) + Send + Sync>;
+
+// Each pattern is also compiled to match against logged bytes, which aren't
+// necessarily valid UTF-8.
+static PATTERNS: RwLock<Vec<(Regex, bytes::Regex)>> = RwLock::new(Vec::new());
+static HOOKS: RwLock<Vec<Hook>> = RwLock::new(Vec::new());
+
+/// Compiles `pattern` and registers it as a redaction.
+///
+/// # Errors
+///
+/// Returns [`LoggerError::InvalidRedactionPattern`] if `pattern` is not a
+/// valid regex.
+///
+/// [`LoggerError::InvalidRedactionPattern`]: crate::LoggerError::InvalidRedactionPattern
+pub fn add_pattern(pattern: &str) -> LoggerResult<()> {
+	add_regex(Regex::new(pattern)?);
+
+	Ok(())
+}
+
+/// Registers an already compiled regex as a redaction.
+pub fn add_regex(regex: Regex) {
+	// Any pattern that compiles as a `Regex` also compiles as a `bytes::Regex`.
+	let bytes_regex =
+		bytes::Regex::new(regex.as_str()).expect("a valid regex should also be a valid bytes regex");
+
+	PATTERNS
+		.write()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+		.push((regex, bytes_regex));
+}
+
+/// Registers a closure that is given every log to modify before it is sent.
+/// Hooks are run after every regex redaction has been applied, in the order
+/// they were added.
+pub fn add_hook(hook: impl Fn(&mut Log) + Send + Sync + 'static) {
+	HOOKS
+		.write()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+		.push(Arc::new(hook));
+}
+
+/// Removes every registered redaction.
+pub fn clear() {
+	PATTERNS
+		.write()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+		.clear();
+	HOOKS
+		.write()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+		.clear();
+}
+
+pub(crate) fn redact(log: &mut Log) {
+	let patterns = PATTERNS
+		.read()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+	for (pattern, _) in patterns.iter() {
+		replace(pattern, &mut log.message);
+		log
+			.code_snippet
+			.values_mut()
+			.for_each(|line| replace(pattern, line));
+		log
+			.stack
+			.iter_mut()
+			.for_each(|frame| replace(pattern, &mut frame.code));
+	}
+
+	drop(patterns);
+
+	// The hooks are cloned out so that a hook can register redactions without
+	// deadlocking.
+	let hooks = HOOKS
+		.read()
+		.unwrap_or_else(|poisoned| poisoned.into_inner())
+		.clone();
+
+	for hook in hooks {
+		hook(log);
+	}
+}
+
+/// Applies every regex redaction to `text`.
+pub(crate) fn redact_text(text: &mut String) {
+	let patterns = PATTERNS
+		.read()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+	for (pattern, _) in patterns.iter() {
+		replace(pattern, text);
+	}
+}
+
+/// Applies every regex redaction to logged `bytes`.
+pub(crate) fn redact_bytes(bytes: &[u8]) -> Cow<'_, [u8]> {
+	let patterns = PATTERNS
+		.read()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+	let mut bytes = Cow::Borrowed(bytes);
+
+	for (_, pattern) in patterns.iter() {
+		if pattern.is_match(&bytes) {
+			bytes = Cow::Owned(
+				pattern
+					.replace_all(&bytes, REDACTED.as_bytes())
+					.into_owned(),
+			);
+		}
+	}
+
+	bytes
+}
+
+fn replace(pattern: &Regex, text: &mut String) {
+	if pattern.is_match(text) {
+		*text = pattern.replace_all(text, REDACTED).into_owned();
+	}
+}
@@ -0,0 +1,166 @@
+//! Limits how many logs are sent from a single call site, so that a log inside
+//! of a hot loop doesn't flood the server.
+//!
+//! Call sites are identified by the file, line and column of the innermost
+//! frame of the log's stack, which is also where its code snippet is taken
+//! from.
+
+use std::{
+	collections::HashMap,
+	sync::{Mutex, OnceLock},
+	time::{Duration, Instant},
+};
+
+use codectrl_protobuf_bindings::data::BacktraceData;
+
+static CALL_SITES: OnceLock<Mutex<HashMap<CallSite, CallSiteState>>> = OnceLock::new();
+
+/// How many logs to send from each call site. By default, every log is sent.
+///
+/// When both a rate limit and a sample rate are set, logs are first sampled and
+/// then rate limited. The first log sent after any have been suppressed carries
+/// a warning with the number of logs that were suppressed. Logs that were
+/// suppressed after the last one sent from a call site, such as at the end of a
+/// loop, are reported in a summary log by [`Logger::flush_suppressed`].
+///
+/// Sampling can be set for every log with [`LoggerConfig::sampling`] or
+/// [`LogBatch::sampling`], or for a single call with [`Logger::log_sampled`].
+///
+/// ```no_run
+/// use codectrl_logger::{Logger, Sampling};
+///
+/// for i in 0..1_000_000 {
+/// 	Logger::log_sampled(i, Sampling::new().one_in(100).rate_limit(10)).unwrap();
+/// }
+/// ```
+///
+/// [`LoggerConfig::sampling`]: crate::LoggerConfig::sampling
+/// [`LogBatch::sampling`]: crate::LogBatch::sampling
+/// [`Logger::log_sampled`]: crate::Logger::log_sampled
+/// [`Logger::flush_suppressed`]: crate::Logger::flush_suppressed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sampling {
+	per_second: Option<u32>,
+	one_in: Option<u32>,
+}
+
+impl Sampling {
+	/// Creates a [`Sampling`] that sends every log.
+	pub fn new() -> Self { Self::default() }
+
+	/// Sends at most `per_second` logs from each call site every second.
+	pub fn rate_limit(mut self, per_second: u32) -> Self {
+		self.per_second = Some(per_second);
+		self
+	}
+
+	/// Only sends one in every `n` logs from each call site, starting with the
+	/// first. A value of `0` or `1` sends every log.
+	pub fn one_in(mut self, n: u32) -> Self {
+		self.one_in = Some(n);
+		self
+	}
+
+	fn is_unlimited(&self) -> bool {
+		self.per_second.is_none() && self.one_in.map_or(true, |n| n <= 1)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CallSite {
+	file_path: String,
+	line_number: u32,
+	column_number: u32,
+}
+
+#[derive(Debug)]
+struct CallSiteState {
+	frame: BacktraceData,
+	seen: u64,
+	window_start: Instant,
+	sent_in_window: u32,
+	suppressed: u64,
+}
+
+/// The outcome of [`admit`] for a single log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Admission {
+	/// The log should be sent. Contains the number of logs from the same call
+	/// site that were suppressed since the last one was sent.
+	Send {
+		suppressed: u64,
+	},
+	Suppress,
+}
+
+/// Decides whether a log created at `frame` should be sent under `sampling`.
+pub(crate) fn admit(frame: &BacktraceData, sampling: &Sampling) -> Admission {
+	if sampling.is_unlimited() {
+		return Admission::Send { suppressed: 0 };
+	}
+
+	let call_site = CallSite {
+		file_path: frame.file_path.clone(),
+		line_number: frame.line_number,
+		column_number: frame.column_number,
+	};
+
+	let now = Instant::now();
+	let mut call_sites = CALL_SITES
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+	let state = call_sites
+		.entry(call_site)
+		.or_insert_with(|| CallSiteState {
+			frame: frame.clone(),
+			seen: 0,
+			window_start: now,
+			sent_in_window: 0,
+			suppressed: 0,
+		});
+
+	state.seen += 1;
+
+	if let Some(n) = sampling.one_in.filter(|n| *n > 1) {
+		if (state.seen - 1) % u64::from(n) != 0 {
+			state.suppressed += 1;
+			return Admission::Suppress;
+		}
+	}
+
+	if let Some(per_second) = sampling.per_second {
+		if now.duration_since(state.window_start) >= Duration::from_secs(1) {
+			state.window_start = now;
+			state.sent_in_window = 0;
+		}
+
+		if state.sent_in_window >= per_second {
+			state.suppressed += 1;
+			return Admission::Suppress;
+		}
+
+		state.sent_in_window += 1;
+	}
+
+	Admission::Send {
+		suppressed: std::mem::take(&mut state.suppressed),
+	}
+}
+
+/// Takes the number of logs suppressed at each call site since the last one
+/// sent from it, along with the frame of the call site, for the call sites that
+/// have any.
+pub(crate) fn take_suppressed() -> Vec<(BacktraceData, u64)> {
+	let mut call_sites = CALL_SITES
+		.get_or_init(Default::default)
+		.lock()
+		.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+	call_sites
+		.values_mut()
+		.filter(|state| state.suppressed > 0)
+		.map(|state| (state.frame.clone(), std::mem::take(&mut state.suppressed)))
+		.collect()
+}
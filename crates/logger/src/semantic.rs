@@ -0,0 +1,71 @@
+//! Locates the item enclosing a logged line so that the whole function (or
+//! `impl` block) can be sent as the code snippet instead of a fixed window of
+//! lines around the call.
+
+use std::ops::RangeInclusive;
+
+use proc_macro2::Span;
+use syn::{
+	spanned::Spanned,
+	visit::{self, Visit},
+	ImplItemFn, ItemFn, ItemImpl, TraitItemFn,
+};
+
+/// Items spanning more lines than this are not sent in full, the regular
+/// `line ± surround` window is used instead.
+pub(crate) const MAX_ITEM_LINES: u32 = 150;
+
+struct EnclosingItem {
+	line: u32,
+	range: Option<RangeInclusive<u32>>,
+}
+
+impl EnclosingItem {
+	// Items are visited outermost first, so the last matching span is the
+	// innermost item containing the line.
+	fn consider(&mut self, span: Span) {
+		let range = span.start().line as u32..=span.end().line as u32;
+
+		if range.contains(&self.line) {
+			self.range = Some(range);
+		}
+	}
+}
+
+impl<'ast> Visit<'ast> for EnclosingItem {
+	fn visit_item_fn(&mut self, item: &'ast ItemFn) {
+		self.consider(item.span());
+		visit::visit_item_fn(self, item);
+	}
+
+	fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+		self.consider(item.span());
+		visit::visit_item_impl(self, item);
+	}
+
+	fn visit_impl_item_fn(&mut self, item: &'ast ImplItemFn) {
+		self.consider(item.span());
+		visit::visit_impl_item_fn(self, item);
+	}
+
+	fn visit_trait_item_fn(&mut self, item: &'ast TraitItemFn) {
+		self.consider(item.span());
+		visit::visit_trait_item_fn(self, item);
+	}
+}
+
+/// Returns the (1-indexed, inclusive) line range of the innermost `fn` or
+/// `impl` item in `source` that contains `line`.
+///
+/// Returns `None` if the source could not be parsed, no item encloses the
+/// line, or the item is longer than [`MAX_ITEM_LINES`].
+pub(crate) fn enclosing_item(source: &str, line: u32) -> Option<RangeInclusive<u32>> {
+	let file = syn::parse_file(source).ok()?;
+	let mut finder = EnclosingItem { line, range: None };
+
+	finder.visit_file(&file);
+
+	finder
+		.range
+		.filter(|range| range.end() - range.start() < MAX_ITEM_LINES)
+}
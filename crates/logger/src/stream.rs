@@ -0,0 +1,513 @@
+//! A long-running stream of logs to the server, where the server acknowledges
+//! or rejects each log individually.
+//!
+//! Logs are sent from a background task. If the connection drops, the task
+//! reconnects and resends every log that wasn't acknowledged, which the server
+//! recognises by its ID and only stores once.
+//!
+//! Servers without the extensions service can't acknowledge logs one by one,
+//! so logs are sent to them in batches with `SendLogs` instead, and each batch
+//! is acknowledged or rejected as a whole.
+
+use std::{
+	collections::VecDeque,
+	fmt::Debug,
+	mem,
+	sync::{Arc, Condvar, Mutex, MutexGuard},
+	time::Duration,
+};
+
+use prost::Message;
+use tokio::{
+	runtime::Runtime,
+	sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+	time::sleep,
+};
+use tokio_stream::{iter, wrappers::UnboundedReceiverStream};
+use tonic::Code;
+
+use codectrl_protobuf_bindings::{
+	data::Log,
+	extensions::{ExtendedLog, ExtendedLoggerClient, LogAck},
+	logs_service::{LoggerClient, RequestStatus},
+};
+
+use crate::{
+	authorised_request, create_log, environment, status_to_error, transport, Logger, LoggerConfig,
+	LoggerError, LoggerResult,
+};
+
+// How many logs can be waiting for an acknowledgement before `LogStream::log`
+// blocks until the server catches up.
+const MAX_IN_FLIGHT: usize = 256;
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+/// A log that the server refused to store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedLog {
+	/// The ID of the rejected log.
+	pub uuid: String,
+	/// Why the server rejected the log.
+	pub reason: String,
+}
+
+/// What happened to the logs sent through a [`LogStream`], returned by
+/// [`LogStream::close`].
+#[derive(Debug, Default)]
+pub struct StreamReport {
+	/// How many logs the server stored.
+	pub acknowledged: usize,
+	/// The logs that the server refused to store.
+	pub rejected: Vec<RejectedLog>,
+	/// The logs that the server never acknowledged, because the stream stopped
+	/// before they could be sent or before their acknowledgement arrived.
+	pub unacknowledged: Vec<Log>,
+	/// Why the stream stopped early, if it did.
+	pub error: Option<LoggerError>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+	in_flight: VecDeque<ExtendedLog>,
+	acknowledged: usize,
+	rejected: Vec<RejectedLog>,
+	error: Option<LoggerError>,
+	finished: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+	state: Mutex<State>,
+	changed: Condvar,
+}
+
+impl Shared {
+	fn lock(&self) -> MutexGuard<'_, State> {
+		self
+			.state
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn acknowledge(&self, ack: LogAck) {
+		let mut state = self.lock();
+
+		let position = state
+			.in_flight
+			.iter()
+			.position(|log| log.log.as_ref().map(|log| &log.uuid) == Some(&ack.uuid));
+
+		// Logs that were resent after reconnecting can be acknowledged twice.
+		let Some(position) = position else {
+			return;
+		};
+
+		let log = state.in_flight.remove(position);
+
+		if ack.status == i32::from(RequestStatus::Confirmed) {
+			if let Some(log) = &log {
+				environment::confirm_sent(log);
+			}
+
+			state.acknowledged += 1;
+		} else {
+			state.rejected.push(RejectedLog {
+				uuid: ack.uuid,
+				reason: ack.message,
+			});
+		}
+
+		self.changed.notify_all();
+	}
+
+	// Acknowledges every log in `batch` with the same result, for servers that
+	// only acknowledge whole batches.
+	fn acknowledge_batch(&self, batch: &[ExtendedLog], status: i32, message: &str) {
+		for log in batch.iter().filter_map(|log| log.log.as_ref()) {
+			self.acknowledge(LogAck {
+				uuid: log.uuid.clone(),
+				status,
+				message: message.to_string(),
+			});
+		}
+	}
+
+	fn finish(&self, error: Option<LoggerError>) {
+		let mut state = self.lock();
+
+		state.error = error;
+		state.finished = true;
+
+		self.changed.notify_all();
+	}
+}
+
+/// Sends logs to the server over a single long-running stream. Created with
+/// [`Logger::stream`] or [`LogStream::new`].
+///
+/// Unlike [`LogBatch`], logs are sent as soon as they are created and the
+/// server acknowledges each one, so a rejected log or a dropped connection
+/// doesn't lose the rest. [`LogStream::log`] blocks while too many logs are
+/// waiting to be acknowledged.
+///
+/// Dropping a [`LogStream`] closes it, waiting for every log to be
+/// acknowledged.
+///
+/// ```no_run
+/// use codectrl_logger::Logger;
+///
+/// let stream = Logger::stream().unwrap();
+///
+/// for i in 0..1000 {
+/// 	stream.log(i).unwrap();
+/// }
+///
+/// let report = stream.close();
+/// assert!(report.unacknowledged.is_empty());
+/// ```
+///
+/// [`LogBatch`]: crate::LogBatch
+#[derive(Debug)]
+pub struct LogStream {
+	config: LoggerConfig,
+	sender: Option<UnboundedSender<ExtendedLog>>,
+	shared: Arc<Shared>,
+	// Only present if the config doesn't have a tokio runtime to run the
+	// background task on.
+	runtime: Option<Runtime>,
+}
+
+impl LogStream {
+	/// Starts streaming logs to the server configured in `config`. The
+	/// connection is made in the background, so connection errors are only
+	/// reported by [`Self::log`] and [`Self::close`].
+	///
+	/// If logging is disabled (see [`Logger::is_enabled`]), no connection is
+	/// made and the stream is already closed, so [`Self::close`] returns an
+	/// empty [`StreamReport`].
+	pub fn new(config: LoggerConfig) -> LoggerResult<Self> {
+		let shared = Arc::new(Shared::default());
+
+		if !Logger::is_enabled() {
+			shared.finish(None);
+
+			return Ok(Self {
+				config,
+				sender: None,
+				shared,
+				runtime: None,
+			});
+		}
+
+		let (sender, receiver) = mpsc::unbounded_channel();
+		let task = run(config.clone(), receiver, Arc::clone(&shared));
+
+		let runtime = if let Some(handle) = &config.tokio_runtime {
+			handle.spawn(task);
+			None
+		} else {
+			let runtime = Runtime::new()?;
+			runtime.spawn(task);
+			Some(runtime)
+		};
+
+		Ok(Self {
+			config,
+			sender: Some(sender),
+			shared,
+			runtime,
+		})
+	}
+
+	/// Creates a log of `message` and queues it to be sent. Blocks if too many
+	/// logs are waiting to be acknowledged by the server.
+	///
+	/// Returns an error if the stream has stopped, in which case the logs that
+	/// weren't sent are returned by [`Self::close`].
+	pub fn log<T: Debug>(&self, message: T) -> LoggerResult<()> {
+		if !Logger::is_enabled() {
+			return Ok(());
+		}
+
+		let Some(log) = create_log(
+			message,
+			Some(self.config.surround),
+			self.config.sampling.as_ref(),
+			None,
+			None,
+		) else {
+			return Ok(());
+		};
+
+		let mut state = self
+			.shared
+			.changed
+			.wait_while(self.shared.lock(), |state| {
+				state.in_flight.len() >= MAX_IN_FLIGHT && !state.finished
+			})
+			.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		if state.finished {
+			let reason = state
+				.error
+				.as_ref()
+				.map_or_else(|| "the stream was closed".to_string(), ToString::to_string);
+
+			return Err(LoggerError::LoggerError(format!(
+				"Log stream has stopped: {reason}"
+			)));
+		}
+
+		state.in_flight.push_back(log.clone());
+		drop(state);
+
+		if let Some(sender) = &self.sender {
+			sender.send(log).ok();
+		}
+
+		Ok(())
+	}
+
+	/// How many logs are waiting to be acknowledged by the server.
+	pub fn pending(&self) -> usize { self.shared.lock().in_flight.len() }
+
+	/// Stops accepting logs and waits for the server to acknowledge every log
+	/// that was sent, or for the stream to give up reconnecting.
+	pub fn close(mut self) -> StreamReport { self.finish() }
+
+	fn finish(&mut self) -> StreamReport {
+		// Closing the channel lets the background task finish once every log has
+		// been acknowledged.
+		self.sender.take();
+
+		let mut state = self
+			.shared
+			.changed
+			.wait_while(self.shared.lock(), |state| !state.finished)
+			.unwrap_or_else(|poisoned| poisoned.into_inner());
+
+		StreamReport {
+			acknowledged: state.acknowledged,
+			rejected: mem::take(&mut state.rejected),
+			unacknowledged: mem::take(&mut state.in_flight)
+				.into_iter()
+				.filter_map(|log| log.log)
+				.collect(),
+			error: state.error.take(),
+		}
+	}
+}
+
+impl Drop for LogStream {
+	fn drop(&mut self) {
+		if self.sender.is_some() {
+			self.finish();
+		}
+
+		if let Some(runtime) = self.runtime.take() {
+			runtime.shutdown_background();
+		}
+	}
+}
+
+async fn run(
+	config: LoggerConfig,
+	mut receiver: UnboundedReceiver<ExtendedLog>,
+	shared: Arc<Shared>,
+) {
+	let mut input_closed = false;
+	// Reset whenever a connection succeeds, so that only consecutive failures
+	// count towards `MAX_RECONNECT_ATTEMPTS`.
+	let mut attempts = 0;
+	// Cleared if the server doesn't accept compressed logs.
+	let mut compression_accepted = config.compression_threshold.is_some();
+	let mut extended = true;
+
+	let error = loop {
+		let result = if extended {
+			stream(
+				&config,
+				compression_accepted,
+				&mut receiver,
+				&shared,
+				&mut input_closed,
+				&mut attempts,
+			)
+			.await
+		} else {
+			send_batches(
+				&config,
+				compression_accepted,
+				&mut receiver,
+				&shared,
+				&mut input_closed,
+				&mut attempts,
+			)
+			.await
+		};
+
+		let error = match result {
+			Ok(()) => break None,
+			// The server doesn't accept compressed logs.
+			Err(LoggerError::TonicStatusCode(status))
+				if compression_accepted && transport::compression_rejected(&status) =>
+			{
+				compression_accepted = false;
+				continue;
+			},
+			// The server doesn't know about the extensions service.
+			Err(LoggerError::TonicStatusCode(status))
+				if extended && status.code() == Code::Unimplemented =>
+			{
+				extended = false;
+				continue;
+			},
+			Err(error) => error,
+		};
+
+		// Retrying won't help if the server won't let us in.
+		let fatal = match &error {
+			LoggerError::TonicStatusCode(status) => status.code() == Code::Unimplemented,
+			LoggerError::AuthenticationError(_) | LoggerError::TlsError(_) => true,
+			_ => false,
+		};
+
+		if input_closed && shared.lock().in_flight.is_empty() {
+			break None;
+		}
+
+		attempts += 1;
+
+		if fatal || attempts > MAX_RECONNECT_ATTEMPTS {
+			break Some(error);
+		}
+
+		sleep(RECONNECT_DELAY).await;
+	};
+
+	shared.finish(error);
+}
+
+// Streams logs over a single connection until every log has been acknowledged
+// after the input was closed, or until the connection fails.
+async fn stream(
+	config: &LoggerConfig,
+	compression_accepted: bool,
+	receiver: &mut UnboundedReceiver<ExtendedLog>,
+	shared: &Shared,
+	input_closed: &mut bool,
+	attempts: &mut u32,
+) -> LoggerResult<()> {
+	let channel = transport::connect(config).await?;
+	let mut log_client = ExtendedLoggerClient::new(channel);
+
+	// Every log of a stream is compressed if any is, so the stream is only
+	// compressed if every log would be, whatever its size.
+	if compression_accepted && transport::should_compress(config, 0) {
+		log_client = log_client.send_gzip();
+	}
+
+	let (outbound, outbound_receiver) = mpsc::unbounded_channel();
+
+	// Every queued log is also in flight, so they are all sent below.
+	while receiver.try_recv().is_ok() {}
+
+	// Resend everything that wasn't acknowledged over the previous connection.
+	// The server ignores the logs it did store, as they keep their IDs.
+	for log in &shared.lock().in_flight {
+		outbound.send(log.clone()).ok();
+	}
+
+	let mut outbound = (!*input_closed).then_some(outbound);
+
+	let request = authorised_request(
+		UnboundedReceiverStream::new(outbound_receiver),
+		config.token.as_deref(),
+	)?;
+	let mut acks = log_client
+		.stream_extended_logs(request)
+		.await
+		.map_err(status_to_error)?
+		.into_inner();
+
+	*attempts = 0;
+
+	loop {
+		tokio::select! {
+			log = receiver.recv(), if outbound.is_some() => match log {
+				Some(log) => {
+					if let Some(outbound) = &outbound {
+						outbound.send(log).ok();
+					}
+				},
+				// Dropping the sender ends the request stream, after which the
+				// server ends the stream of acknowledgements.
+				None => {
+					*input_closed = true;
+					outbound = None;
+				},
+			},
+			ack = acks.message() => match ack.map_err(status_to_error)? {
+				Some(ack) => shared.acknowledge(ack),
+				None if *input_closed => return Ok(()),
+				None => return Err(LoggerError::LoggerError(
+					"The server closed the log stream".to_string(),
+				)),
+			},
+		}
+	}
+}
+
+// Sends the logs in flight in batches with `SendLogs`, for servers without the
+// extensions service, until every log has been acknowledged after the input was
+// closed, or until a request fails.
+async fn send_batches(
+	config: &LoggerConfig,
+	compression_accepted: bool,
+	receiver: &mut UnboundedReceiver<ExtendedLog>,
+	shared: &Shared,
+	input_closed: &mut bool,
+	attempts: &mut u32,
+) -> LoggerResult<()> {
+	let channel = transport::connect(config).await?;
+
+	loop {
+		// Every queued log is also in flight, so the channel is only used to wait
+		// for new logs.
+		while receiver.try_recv().is_ok() {}
+
+		let batch = shared.lock().in_flight.iter().cloned().collect::<Vec<_>>();
+
+		if batch.is_empty() {
+			if *input_closed {
+				return Ok(());
+			}
+
+			if receiver.recv().await.is_none() {
+				*input_closed = true;
+			}
+
+			continue;
+		}
+
+		let logs = batch
+			.iter()
+			.filter_map(|log| log.log.clone())
+			.collect::<Vec<_>>();
+		let encoded_len = logs.iter().map(Message::encoded_len).sum();
+		let mut log_client = LoggerClient::new(channel.clone());
+
+		if compression_accepted && transport::should_compress(config, encoded_len) {
+			log_client = log_client.send_gzip();
+		}
+		let result = log_client
+			.send_logs(authorised_request(iter(logs), config.token.as_deref())?)
+			.await
+			.map_err(status_to_error)?
+			.into_inner();
+
+		*attempts = 0;
+		shared.acknowledge_batch(&batch, result.status, &result.message);
+	}
+}
@@ -0,0 +1,238 @@
+//! Tags logs with the test that was running when they were created, so that
+//! CodeCTRL can group them under each test along with whether it passed.
+//!
+//! When running under `cargo test`, the test is detected from the name of the
+//! thread it runs on, which the test harness names after the test. Logs
+//! created from other threads can't be attributed this way, and a detected
+//! test is only reported once it fails, so annotating tests with
+//! [`macro@test`] is preferred.
+//!
+//! [`macro@test`]: crate::test
+
+use std::{
+	cell::{Cell, RefCell},
+	env, panic,
+	sync::{Once, OnceLock},
+	thread,
+};
+
+use tonic::Code;
+use uuid::Uuid;
+
+use codectrl_protobuf_bindings::extensions::{
+	ExtendedLoggerClient, TestMetadata, TestOutcome, TestResult,
+};
+
+use crate::{
+	authorised_request, check_request_result, status_to_error, transport, Logger, LoggerConfig,
+	LoggerResult,
+};
+
+static RUN_ID: OnceLock<String> = OnceLock::new();
+static IS_TEST_BINARY: OnceLock<bool> = OnceLock::new();
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+	// The test started with `TestCase::start` on this thread.
+	static CURRENT: RefCell<Option<String>> = const { RefCell::new(None) };
+	// Whether a log on this thread was tagged with a test detected from the
+	// thread's name, in which case a panic on this thread fails that test.
+	static DETECTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// How a test function's return value decides whether the test passed. This
+/// is implemented for the return types allowed by `#[test]`.
+pub trait Outcome {
+	fn passed(&self) -> bool;
+}
+
+impl Outcome for () {
+	fn passed(&self) -> bool { true }
+}
+
+impl<T, E> Outcome for Result<T, E> {
+	fn passed(&self) -> bool { self.is_ok() }
+}
+
+/// A test that is running on the current thread. Every log created on this
+/// thread until the [`TestCase`] is finished or dropped is tagged with it.
+///
+/// This is used by the code generated by [`macro@test`], which should be
+/// preferred over using it directly.
+///
+/// ```no_run
+/// use codectrl_logger::{test_case::TestCase, Logger};
+///
+/// let test = TestCase::start("parses_config");
+/// Logger::log_message("Parsing...").unwrap();
+/// test.finish(true);
+/// ```
+///
+/// [`macro@test`]: crate::test
+#[derive(Debug)]
+#[must_use = "the test's result is only reported by `TestCase::finish` or when it is dropped"]
+pub struct TestCase {
+	name: String,
+	should_panic: bool,
+	finished: bool,
+}
+
+impl TestCase {
+	/// Starts the test called `name` on the current thread.
+	pub fn start(name: impl Into<String>) -> Self {
+		let name = name.into();
+
+		CURRENT.with(|current| *current.borrow_mut() = Some(name.clone()));
+
+		Self {
+			name,
+			should_panic: false,
+			finished: false,
+		}
+	}
+
+	// Names the test in the same way as the test harness, which leaves out the
+	// name of the crate.
+	#[doc(hidden)]
+	pub fn for_function(module_path: &str, function: &str) -> Self {
+		match module_path.split_once("::") {
+			Some((_, module)) => Self::start(format!("{module}::{function}")),
+			None => Self::start(function),
+		}
+	}
+
+	/// Marks the test as one that passes by panicking, as with
+	/// `#[should_panic]`.
+	pub fn should_panic(mut self) -> Self {
+		self.should_panic = true;
+		self
+	}
+
+	/// Finishes the test, reporting whether it `passed`.
+	pub fn finish(mut self, passed: bool) { self.report(passed && !self.should_panic); }
+
+	fn report(&mut self, passed: bool) {
+		self.finished = true;
+
+		CURRENT.with(|current| current.borrow_mut().take());
+
+		report(&self.name, passed);
+	}
+}
+
+impl Drop for TestCase {
+	fn drop(&mut self) {
+		if !self.finished {
+			// Dropped while unwinding from a panic in the test.
+			let passed = thread::panicking() == self.should_panic;
+			self.report(passed);
+		}
+	}
+}
+
+/// The test running on the current thread, if there is one.
+pub(crate) fn current() -> Option<TestMetadata> {
+	let name = CURRENT
+		.with(|current| current.borrow().clone())
+		.or_else(detect)?;
+
+	Some(TestMetadata {
+		run_id: run_id().to_string(),
+		name,
+	})
+}
+
+// The test harness runs each test on a thread named after the test, and cargo
+// builds test binaries into the `deps` directory.
+fn detect() -> Option<String> {
+	let is_test_binary = *IS_TEST_BINARY.get_or_init(|| {
+		env::current_exe()
+			.ok()
+			.and_then(|path| path.parent()?.file_name().map(|name| name == "deps"))
+			.unwrap_or_default()
+	});
+
+	if !is_test_binary {
+		return None;
+	}
+
+	let name = thread::current()
+		.name()
+		.filter(|name| *name != "main")?
+		.to_string();
+
+	DETECTED.with(|detected| detected.set(true));
+	install_panic_hook();
+
+	Some(name)
+}
+
+// Reports a test detected from the thread's name as failed when it panics, as
+// there is no other way of knowing when it finishes.
+fn install_panic_hook() {
+	PANIC_HOOK.call_once(|| {
+		let previous = panic::take_hook();
+
+		panic::set_hook(Box::new(move |info| {
+			previous(info);
+
+			let detected = DETECTED.with(|detected| detected.replace(false));
+			let started = CURRENT.with(|current| current.borrow().is_some());
+
+			if detected && !started {
+				if let Some(name) = thread::current().name() {
+					report(name, false);
+				}
+			}
+		}));
+	});
+}
+
+fn run_id() -> &'static str { RUN_ID.get_or_init(|| Uuid::new_v4().hyphenated().to_string()) }
+
+fn report(name: &str, passed: bool) {
+	if !Logger::is_enabled() {
+		return;
+	}
+
+	let result = TestResult {
+		run_id: run_id().to_string(),
+		name: name.to_string(),
+		outcome: if passed {
+			TestOutcome::Passed
+		} else {
+			TestOutcome::Failed
+		}
+		.into(),
+	};
+
+	// Reported from a new thread, as the test may be running inside of a tokio
+	// runtime, or be panicking, where blocking on a new runtime would panic.
+	let error = thread::spawn(move || {
+		let config = LoggerConfig::global();
+
+		config
+			.block_on(send_result(result, config))
+			.and_then(|result| result)
+			.err()
+	})
+	.join()
+	.ok()
+	.flatten();
+
+	if let Some(error) = error {
+		eprintln!("Could not report the result of {name}: {error}");
+	}
+}
+
+async fn send_result(result: TestResult, config: &LoggerConfig) -> LoggerResult<()> {
+	let channel = transport::connect(config).await?;
+	let mut client = ExtendedLoggerClient::new(channel);
+	let request = authorised_request(result, config.token.as_deref())?;
+
+	match client.report_test_result(request).await {
+		// Older servers don't group logs by test.
+		Err(status) if status.code() == Code::Unimplemented => Ok(()),
+		response => check_request_result(response.map_err(status_to_error)?.into_inner()),
+	}
+}
@@ -0,0 +1,532 @@
+//! Helpers for checking what was logged from inside a test suite, without
+//! needing a CodeCTRL server to be running.
+//!
+//! ```no_run
+//! use codectrl_logger::{testing::CaptureServer, Logger};
+//!
+//! let server = CaptureServer::start().unwrap();
+//!
+//! Logger::log(
+//! 	"Hello, world!",
+//! 	None,
+//! 	Some(server.host()),
+//! 	Some(server.port()),
+//! 	None,
+//! )
+//! .unwrap();
+//!
+//! server.assert_logged(|log| log.message.contains("Hello, world!"));
+//! server.assert_logged(|log| {
+//! 	log
+//! 		.metadata
+//! 		.as_ref()
+//! 		.is_some_and(|metadata| !metadata.session_id.is_empty())
+//! });
+//! ```
+
+use std::{
+	collections::HashMap,
+	io,
+	net::TcpListener as StdTcpListener,
+	ops::Deref,
+	sync::{Arc, Mutex, MutexGuard},
+	thread::{self, JoinHandle},
+};
+
+use futures_util::StreamExt;
+use tokio::{
+	net::TcpListener,
+	runtime::Runtime,
+	sync::{mpsc, oneshot},
+};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+#[cfg(test)]
+use tonic::transport::Identity;
+use tonic::{
+	metadata::MetadataMap,
+	transport::{Server, ServerTlsConfig},
+	Request, Response, Status, Streaming,
+};
+
+use codectrl_protobuf_bindings::{
+	data::Log,
+	extensions::{
+		CallSite, CallSiteRule, CallSiteRules, ExtendedLog, ExtendedLogClientService,
+		ExtendedLogClientTrait, LogAck, LogId, LogMetadata, TestResult,
+	},
+	logs_service::{LogClientService, LogClientTrait, RequestResult, RequestStatus},
+};
+
+use crate::TOKEN_METADATA_KEY;
+
+/// A [`Log`] received by a [`CaptureServer`], along with the [`LogMetadata`]
+/// it was sent with.
+///
+/// Dereferences to the [`Log`], so that predicates can use its fields
+/// directly.
+///
+/// [`Log`]: codectrl_protobuf_bindings::data::Log
+/// [`LogMetadata`]: codectrl_protobuf_bindings::extensions::LogMetadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedLog {
+	pub log: Log,
+	/// `None` if the log was sent without metadata, such as through `SendLog`.
+	pub metadata: Option<LogMetadata>,
+}
+
+impl Deref for CapturedLog {
+	type Target = Log;
+
+	fn deref(&self) -> &Log { &self.log }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Capture {
+	logs: Arc<Mutex<Vec<CapturedLog>>>,
+	test_results: Arc<Mutex<Vec<TestResult>>>,
+	request_metadata: Arc<Mutex<Vec<MetadataMap>>>,
+	token: Arc<Mutex<Option<String>>>,
+	call_sites: Arc<Mutex<HashMap<String, CallSite>>>,
+	log_points: Arc<Mutex<LogPoints>>,
+}
+
+#[derive(Debug, Default)]
+struct LogPoints {
+	rules: HashMap<String, CallSiteRule>,
+	watchers: Vec<mpsc::Sender<Result<CallSiteRules, Status>>>,
+}
+
+impl Capture {
+	fn lock(&self) -> MutexGuard<'_, Vec<CapturedLog>> {
+		self
+			.logs
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn push(&self, log: Log, metadata: Option<LogMetadata>) {
+		self.lock().push(CapturedLog { log, metadata });
+	}
+
+	fn lock_test_results(&self) -> MutexGuard<'_, Vec<TestResult>> {
+		self
+			.test_results
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn lock_request_metadata(&self) -> MutexGuard<'_, Vec<MetadataMap>> {
+		self
+			.request_metadata
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn lock_call_sites(&self) -> MutexGuard<'_, HashMap<String, CallSite>> {
+		self
+			.call_sites
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn lock_log_points(&self) -> MutexGuard<'_, LogPoints> {
+		self
+			.log_points
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	fn lock_token(&self) -> MutexGuard<'_, Option<String>> {
+		self
+			.token
+			.lock()
+			.unwrap_or_else(|poisoned| poisoned.into_inner())
+	}
+
+	// Records the metadata of the request, then rejects it if a token is
+	// required and the request doesn't carry it.
+	fn authorise<T>(&self, request: &Request<T>) -> Result<(), Status> {
+		let metadata = request.metadata().clone();
+		let authorised = self.lock_token().as_ref().map_or(true, |token| {
+			metadata
+				.get(TOKEN_METADATA_KEY)
+				.and_then(|value| value.to_str().ok())
+				== Some(format!("Bearer {token}").as_str())
+		});
+
+		self.lock_request_metadata().push(metadata);
+
+		if authorised {
+			Ok(())
+		} else {
+			Err(Status::unauthenticated("A valid API token is required"))
+		}
+	}
+
+	fn confirm(amount: usize) -> Response<RequestResult> {
+		Response::new(RequestResult {
+			message: format!("{amount} logs captured!"),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		})
+	}
+}
+
+#[tonic::async_trait]
+impl LogClientTrait for Capture {
+	async fn send_log(&self, request: Request<Log>) -> Result<Response<RequestResult>, Status> {
+		self.authorise(&request)?;
+
+		self.push(request.into_inner(), None);
+
+		Ok(Self::confirm(1))
+	}
+
+	async fn send_logs(
+		&self,
+		request: Request<Streaming<Log>>,
+	) -> Result<Response<RequestResult>, Status> {
+		self.authorise(&request)?;
+
+		let mut stream = request.into_inner();
+		let mut amount = 0;
+
+		while let Some(log) = stream.next().await {
+			self.push(log?, None);
+			amount += 1;
+		}
+
+		Ok(Self::confirm(amount))
+	}
+}
+
+#[tonic::async_trait]
+impl ExtendedLogClientTrait for Capture {
+	async fn send_extended_log(
+		&self,
+		request: Request<ExtendedLog>,
+	) -> Result<Response<RequestResult>, Status> {
+		self.authorise(&request)?;
+
+		let ExtendedLog {
+			log: Some(log),
+			metadata,
+		} = request.into_inner()
+		else {
+			return Err(Status::invalid_argument("No log was supplied"));
+		};
+
+		self.push(log, metadata);
+
+		Ok(Self::confirm(1))
+	}
+
+	async fn send_extended_logs(
+		&self,
+		request: Request<Streaming<ExtendedLog>>,
+	) -> Result<Response<RequestResult>, Status> {
+		self.authorise(&request)?;
+
+		let mut stream = request.into_inner();
+		let mut amount = 0;
+
+		while let Some(extended_log) = stream.next().await {
+			let extended_log = extended_log?;
+
+			if let Some(log) = extended_log.log {
+				self.push(log, extended_log.metadata);
+				amount += 1;
+			}
+		}
+
+		Ok(Self::confirm(amount))
+	}
+
+	type StreamExtendedLogsStream = ReceiverStream<Result<LogAck, Status>>;
+
+	async fn stream_extended_logs(
+		&self,
+		request: Request<Streaming<ExtendedLog>>,
+	) -> Result<Response<Self::StreamExtendedLogsStream>, Status> {
+		self.authorise(&request)?;
+
+		let mut stream = request.into_inner();
+		let (tx, rx) = mpsc::channel(16);
+		let capture = self.clone();
+
+		tokio::spawn(async move {
+			while let Some(Ok(extended_log)) = stream.next().await {
+				let ExtendedLog {
+					log: Some(log),
+					metadata,
+				} = extended_log
+				else {
+					continue;
+				};
+
+				let ack = LogAck {
+					uuid: log.uuid.clone(),
+					status: RequestStatus::Confirmed.into(),
+					message: String::new(),
+				};
+
+				capture.push(log, metadata);
+
+				if tx.send(Ok(ack)).await.is_err() {
+					break;
+				}
+			}
+		});
+
+		Ok(Response::new(ReceiverStream::new(rx)))
+	}
+
+	async fn register_call_site(
+		&self,
+		request: Request<CallSite>,
+	) -> Result<Response<RequestResult>, Status> {
+		self.authorise(&request)?;
+
+		let call_site = request.into_inner();
+		self
+			.lock_call_sites()
+			.insert(call_site.id.clone(), call_site);
+
+		Ok(Response::new(RequestResult {
+			message: "Call site registered!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	type WatchCallSiteRulesStream = ReceiverStream<Result<CallSiteRules, Status>>;
+
+	async fn watch_call_site_rules(
+		&self,
+		request: Request<()>,
+	) -> Result<Response<Self::WatchCallSiteRulesStream>, Status> {
+		self.authorise(&request)?;
+
+		let (tx, rx) = mpsc::channel(16);
+		let mut log_points = self.lock_log_points();
+
+		// The first message contains every rule, and is sent while the rules are
+		// locked so that no change is missed before the watcher is added.
+		let rules = CallSiteRules {
+			rules: log_points.rules.values().cloned().collect(),
+		};
+		tx.try_send(Ok(rules)).ok();
+		log_points.watchers.push(tx);
+
+		Ok(Response::new(ReceiverStream::new(rx)))
+	}
+
+	// Breakpoints are resumed straight away, so that tests don't block.
+	async fn wait_for_resume(
+		&self,
+		request: Request<LogId>,
+	) -> Result<Response<RequestResult>, Status> {
+		self.authorise(&request)?;
+
+		Ok(Response::new(RequestResult {
+			message: "Breakpoint resumed!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	async fn report_test_result(
+		&self,
+		request: Request<TestResult>,
+	) -> Result<Response<RequestResult>, Status> {
+		self.authorise(&request)?;
+
+		self.lock_test_results().push(request.into_inner());
+
+		Ok(Response::new(RequestResult {
+			message: "Test result captured!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+}
+
+/// An in-process gRPC server that records every [`Log`] sent to it, and the
+/// [`LogMetadata`] sent with it, so that tests can assert on what was logged.
+///
+/// The server listens on an ephemeral port on `127.0.0.1` and runs on its own
+/// thread, so it can be used from both synchronous and asynchronous tests. It
+/// is shut down when dropped.
+///
+/// [`Log`]: codectrl_protobuf_bindings::data::Log
+/// [`LogMetadata`]: codectrl_protobuf_bindings::extensions::LogMetadata
+#[derive(Debug)]
+pub struct CaptureServer {
+	capture: Capture,
+	port: String,
+	shutdown: Option<oneshot::Sender<()>>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl CaptureServer {
+	/// Starts a new [`CaptureServer`] on an ephemeral port.
+	pub fn start() -> io::Result<Self> { Self::start_with(None) }
+
+	/// Starts a new [`CaptureServer`] that is connected to over TLS, presenting
+	/// the certificate of `identity`.
+	#[cfg(test)]
+	pub(crate) fn start_tls(identity: Identity) -> io::Result<Self> {
+		Self::start_with(Some(ServerTlsConfig::new().identity(identity)))
+	}
+
+	fn start_with(tls: Option<ServerTlsConfig>) -> io::Result<Self> {
+		let listener = StdTcpListener::bind("127.0.0.1:0")?;
+		listener.set_nonblocking(true)?;
+
+		let port = listener.local_addr()?.port().to_string();
+
+		let capture = Capture::default();
+		let (shutdown, on_shutdown) = oneshot::channel();
+		let runtime = Runtime::new()?;
+
+		let thread = thread::spawn({
+			let capture = capture.clone();
+
+			move || {
+				runtime.block_on(async move {
+					let listener = TcpListener::from_std(listener).expect("Could not start listener");
+
+					let mut server = Server::builder();
+
+					if let Some(tls) = tls {
+						server = server.tls_config(tls).expect("Could not configure TLS");
+					}
+
+					let result = server
+						.add_service(LogClientService::new(capture.clone()).accept_gzip())
+						.add_service(ExtendedLogClientService::new(capture).accept_gzip())
+						.serve_with_incoming_shutdown(TcpListenerStream::new(listener), async {
+							on_shutdown.await.ok();
+						})
+						.await;
+
+					if let Err(error) = result {
+						eprintln!("CaptureServer stopped unexpectedly: {error}");
+					}
+				});
+			}
+		});
+
+		Ok(Self {
+			capture,
+			port,
+			shutdown: Some(shutdown),
+			thread: Some(thread),
+		})
+	}
+
+	/// The host to pass to [`Logger`] or [`LogBatch::host`].
+	///
+	/// [`Logger`]: crate::Logger
+	/// [`LogBatch::host`]: crate::LogBatch::host
+	pub fn host(&self) -> &'static str { "127.0.0.1" }
+
+	/// The port to pass to [`Logger`] or [`LogBatch::port`].
+	///
+	/// [`Logger`]: crate::Logger
+	/// [`LogBatch::port`]: crate::LogBatch::port
+	pub fn port(&self) -> &str { &self.port }
+
+	/// Returns a copy of every log received so far, in the order they were
+	/// received.
+	pub fn logs(&self) -> Vec<CapturedLog> { self.capture.lock().clone() }
+
+	/// Returns a copy of every [`TestResult`] reported so far, in the order
+	/// they were reported.
+	///
+	/// [`TestResult`]: codectrl_protobuf_bindings::extensions::TestResult
+	pub fn test_results(&self) -> Vec<TestResult> { self.capture.lock_test_results().clone() }
+
+	/// Returns a copy of the metadata of every request received so far, in the
+	/// order they were received, such as the `authorization` header carrying
+	/// the API token.
+	pub fn request_metadata(&self) -> Vec<MetadataMap> {
+		self.capture.lock_request_metadata().clone()
+	}
+
+	/// Returns every call site registered so far by a logger with
+	/// [`LoggerConfig::remote_log_points`] enabled.
+	///
+	/// [`LoggerConfig::remote_log_points`]: crate::LoggerConfig::remote_log_points
+	pub fn call_sites(&self) -> Vec<CallSite> {
+		self.capture.lock_call_sites().values().cloned().collect()
+	}
+
+	/// Sets the rule of a call site, as the log points view of the GUI does, and
+	/// sends it to every logger watching for rules.
+	pub fn set_call_site_rule(&self, rule: CallSiteRule) {
+		let mut log_points = self.capture.lock_log_points();
+		let update = CallSiteRules {
+			rules: vec![rule.clone()],
+		};
+
+		log_points.rules.insert(rule.call_site_id.clone(), rule);
+		log_points
+			.watchers
+			.retain(|watcher| watcher.try_send(Ok(update.clone())).is_ok());
+	}
+
+	/// Rejects every following request that doesn't carry `token` as a bearer
+	/// token with an `Unauthenticated` status, like a server that requires
+	/// authentication.
+	pub fn require_token(&self, token: impl Into<String>) {
+		*self.capture.lock_token() = Some(token.into());
+	}
+
+	/// Forgets every log, [`TestResult`] and request metadata received so far.
+	///
+	/// [`TestResult`]: codectrl_protobuf_bindings::extensions::TestResult
+	pub fn clear(&self) {
+		self.capture.lock().clear();
+		self.capture.lock_test_results().clear();
+		self.capture.lock_request_metadata().clear();
+	}
+
+	/// Panics if no received log matches `predicate`.
+	#[track_caller]
+	pub fn assert_logged(&self, predicate: impl Fn(&CapturedLog) -> bool) {
+		let logs = self.logs();
+
+		assert!(
+			logs.iter().any(predicate),
+			"No matching log was captured. Captured logs: {:#?}",
+			logs.iter().map(|log| &log.message).collect::<Vec<_>>()
+		);
+	}
+
+	/// Panics if any received log matches `predicate`.
+	#[track_caller]
+	pub fn assert_not_logged(&self, predicate: impl Fn(&CapturedLog) -> bool) {
+		let logs = self.logs();
+
+		if let Some(log) = logs.iter().find(|log| predicate(log)) {
+			panic!("A matching log was captured: {log:#?}");
+		}
+	}
+}
+
+impl Drop for CaptureServer {
+	fn drop(&mut self) {
+		// The server waits for every response to finish before shutting down, and
+		// the streams of rules would otherwise never finish.
+		self.capture.lock_log_points().watchers.clear();
+
+		if let Some(shutdown) = self.shutdown.take() {
+			shutdown.send(()).ok();
+		}
+
+		if let Some(thread) = self.thread.take() {
+			thread.join().ok();
+		}
+	}
+}
@@ -1,8 +1,7 @@
 #![allow(dead_code)]
 #![cfg(test)]
 
-use crate::Logger;
-use std::{thread::sleep, time::Duration};
+use crate::{testing::CaptureServer, Logger};
 
 #[test]
 fn log() { log_layer_2(); }
@@ -16,16 +15,515 @@ fn log_when_env() { log_when_env_layer_2() }
 #[test]
 fn log_batch() { log_batch_layer_2() }
 
+#[test]
+fn sampling() {
+	use crate::{LoggerConfig, Sampling};
+
+	let server = CaptureServer::start().unwrap();
+	let config = LoggerConfig::new()
+		.host(server.host())
+		.port(server.port())
+		.sampling(Sampling::new().one_in(5));
+
+	for i in 0..10 {
+		if let Err(e) = Logger::log_with_config(format!("Sampled {i}"), &config) {
+			panic!("{e}");
+		}
+	}
+
+	assert_eq!(server.logs().len(), 2);
+	server.assert_logged(|log| log.message.contains("Sampled 0"));
+	server.assert_logged(|log| {
+		log.message.contains("Sampled 5")
+			&& log
+				.warnings
+				.iter()
+				.any(|warning| warning.starts_with("4 logs"))
+	});
+
+	if let Err(e) = Logger::flush_suppressed_with_config(&config) {
+		panic!("{e}");
+	}
+
+	server.assert_logged(|log| {
+		log.message == "4 logs from this call site were suppressed by sampling"
+			&& log
+				.stack
+				.last()
+				.is_some_and(|frame| frame.code.contains("Sampled"))
+	});
+}
+
+#[test]
+fn log_points() {
+	use std::{
+		thread,
+		time::{Duration, Instant},
+	};
+
+	use codectrl_protobuf_bindings::{data::BacktraceData, extensions::CallSiteRule};
+
+	use crate::{
+		log_points::{self, Control},
+		sampling::{self, Admission},
+		LoggerConfig,
+	};
+
+	// Rules are applied as they arrive from the server, which happens on another
+	// thread.
+	#[track_caller]
+	fn eventually(condition: impl Fn() -> bool) {
+		let deadline = Instant::now() + Duration::from_secs(5);
+
+		while !condition() {
+			assert!(Instant::now() < deadline, "The condition was never met");
+			thread::sleep(Duration::from_millis(10));
+		}
+	}
+
+	let server = CaptureServer::start().unwrap();
+	// Rules are only watched for using the first config given, so every log
+	// point is tested here.
+	let config: &'static LoggerConfig = Box::leak(Box::new(
+		LoggerConfig::new()
+			.host(server.host())
+			.port(server.port())
+			.remote_log_points(),
+	));
+
+	let frame = |line_number| BacktraceData {
+		name: "log_points".into(),
+		file_path: "src/log_points_test.rs".into(),
+		line_number,
+		column_number: 1,
+		code: String::new(),
+	};
+	let rule = |frame: &BacktraceData, enabled, one_in| CallSiteRule {
+		call_site_id: log_points::call_site(frame).id,
+		enabled,
+		rate_limit: None,
+		one_in,
+	};
+	let (disabled, sampled, unruled) = (frame(1), frame(2), frame(3));
+
+	server.set_call_site_rule(rule(&disabled, false, None));
+	server.set_call_site_rule(rule(&sampled, true, Some(3)));
+
+	eventually(|| {
+		matches!(
+			log_points::control_with(&disabled, config),
+			Control::Suppress
+		)
+	});
+	assert!(matches!(
+		log_points::control_with(&unruled, config),
+		Control::Send
+	));
+
+	let sent = (0..6)
+		.filter(|_| match log_points::control_with(&sampled, config) {
+			Control::Sample(sampling) =>
+				matches!(sampling::admit(&sampled, &sampling), Admission::Send { .. }),
+			_ => panic!("The sampled call site wasn't sampled"),
+		})
+		.count();
+
+	assert_eq!(sent, 2);
+
+	server.set_call_site_rule(rule(&disabled, true, None));
+
+	eventually(|| matches!(log_points::control_with(&disabled, config), Control::Send));
+	eventually(|| server.call_sites().len() == 3);
+}
+
+#[test]
+fn log_stream() {
+	use crate::{LogStream, LoggerConfig};
+
+	let server = CaptureServer::start().unwrap();
+	let stream = LogStream::new(LoggerConfig::new().host(server.host()).port(server.port())).unwrap();
+
+	for i in 0..3 {
+		if let Err(e) = stream.log(format!("Streamed {i}")) {
+			panic!("{e}");
+		}
+	}
+
+	let report = stream.close();
+
+	assert!(report.error.is_none(), "{:?}", report.error);
+	assert_eq!(report.acknowledged, 3);
+	assert!(report.unacknowledged.is_empty());
+	server.assert_logged(|log| log.message.contains("Streamed 2"));
+}
+
+#[test]
+fn authentication() {
+	use crate::{LoggerConfig, LoggerError};
+
+	let server = CaptureServer::start().unwrap();
+	server.require_token("test-token");
+
+	let config = LoggerConfig::new().host(server.host()).port(server.port());
+
+	match Logger::log_with_config("Unauthenticated hello", &config) {
+		Err(LoggerError::AuthenticationError(_)) => {},
+		result => panic!("Expected an authentication error, got {result:?}"),
+	}
+
+	if let Err(e) = Logger::log_with_config("Authenticated hello", &config.token("test-token")) {
+		panic!("{e}");
+	}
+
+	server.assert_not_logged(|log| log.message.contains("Unauthenticated hello"));
+	server.assert_logged(|log| log.message.contains("Authenticated hello"));
+	assert!(server.request_metadata().iter().any(|metadata| {
+		metadata
+			.get("authorization")
+			.and_then(|value| value.to_str().ok())
+			== Some("Bearer test-token")
+	}));
+}
+
+#[test]
+fn pause_here() {
+	use std::time::Duration;
+
+	use crate::LoggerConfig;
+
+	let server = CaptureServer::start().unwrap();
+	let config = LoggerConfig::new()
+		.host(server.host())
+		.port(server.port())
+		.breakpoint_timeout(Duration::from_secs(30));
+
+	// CaptureServer resumes every breakpoint straight away.
+	match Logger::pause_here_with_config("Paused hello", &config) {
+		Ok(resumed) => assert!(resumed),
+		Err(e) => panic!("{e}"),
+	}
+
+	server.assert_logged(|log| {
+		log.message.contains("Paused hello")
+			&& log
+				.metadata
+				.as_ref()
+				.and_then(|metadata| metadata.breakpoint.as_ref())
+				.is_some_and(|breakpoint| breakpoint.timeout_millis == 30_000)
+	});
+}
+
+#[test]
+fn pinned_certificate() {
+	use tonic::transport::Identity;
+
+	use crate::{LoggerConfig, TlsConfig};
+
+	let server = CaptureServer::start_tls(Identity::from_pem(
+		include_str!("testdata/server.pem"),
+		include_str!("testdata/server.key"),
+	))
+	.unwrap();
+	let config = LoggerConfig::new()
+		.host(server.host())
+		.port(server.port())
+		.tls(TlsConfig::new().pinned_certificate(include_str!("testdata/server.pem")));
+
+	if let Err(e) = Logger::log_with_config("Pinned hello", &config) {
+		panic!("{e}");
+	}
+
+	server.assert_logged(|log| log.message.contains("Pinned hello"));
+}
+
+#[test]
+fn pinned_certificate_mismatch() {
+	use tonic::transport::Identity;
+
+	use crate::{LoggerConfig, LoggerError, TlsConfig};
+
+	let server = CaptureServer::start_tls(Identity::from_pem(
+		include_str!("testdata/server.pem"),
+		include_str!("testdata/server.key"),
+	))
+	.unwrap();
+	let config = LoggerConfig::new()
+		.host(server.host())
+		.port(server.port())
+		.tls(TlsConfig::new().pinned_certificate(include_str!("testdata/other.pem")));
+
+	match Logger::log_with_config("Mismatched hello", &config) {
+		Err(LoggerError::TlsError(_)) => {},
+		result => panic!("Expected a TLS error, got {result:?}"),
+	}
+
+	server.assert_not_logged(|log| log.message.contains("Mismatched hello"));
+}
+
+#[test]
+fn failed_auth_status() {
+	use codectrl_protobuf_bindings::{
+		auth_service::VerifyTokenRequestResult,
+		logs_service::{RequestResult, RequestStatus},
+	};
+
+	use crate::LoggerError;
+
+	let result = |auth_status| RequestResult {
+		message: "Rejected".into(),
+		status: RequestStatus::Error.into(),
+		auth_status,
+	};
+
+	assert!(matches!(
+		crate::check_request_result(result(Some(VerifyTokenRequestResult { result: false }))),
+		Err(LoggerError::AuthenticationError(_))
+	));
+	assert!(matches!(
+		crate::check_request_result(result(Some(VerifyTokenRequestResult { result: true }))),
+		Err(LoggerError::LogServerError { .. })
+	));
+	assert!(matches!(
+		crate::check_request_result(result(None)),
+		Err(LoggerError::LogServerError { .. })
+	));
+}
+
+#[test]
+fn log_stream_compression() {
+	use crate::{LogStream, LoggerConfig};
+
+	let compressed = |config: LoggerConfig| {
+		let server = CaptureServer::start().unwrap();
+		let stream = LogStream::new(config.host(server.host()).port(server.port())).unwrap();
+
+		if let Err(e) = stream.log("Small streamed hello") {
+			panic!("{e}");
+		}
+
+		let report = stream.close();
+		assert!(report.error.is_none(), "{:?}", report.error);
+
+		server
+			.request_metadata()
+			.iter()
+			.any(|metadata| metadata.contains_key("grpc-encoding"))
+	};
+
+	assert!(!compressed(LoggerConfig::new()));
+	assert!(compressed(LoggerConfig::new().compression_threshold(0)));
+}
+
+#[test]
+fn compression() {
+	use crate::LoggerConfig;
+
+	let server = CaptureServer::start().unwrap();
+	let config = LoggerConfig::new()
+		.host(server.host())
+		.port(server.port())
+		.compression_threshold(0);
+
+	if let Err(e) = Logger::log_with_config("Compressed hello", &config) {
+		panic!("{e}");
+	}
+
+	server.assert_logged(|log| log.message.contains("Compressed hello"));
+}
+
+#[test]
+fn test_case() {
+	use crate::test_case::{self, TestCase};
+
+	let test = TestCase::for_function(module_path!(), "named_test");
+	let current = test_case::current().map(|test| test.name);
+	test.finish(true);
+
+	assert_eq!(current.as_deref(), Some("tests::named_test"));
+}
+
+#[test]
+fn assertion() {
+	use std::panic;
+
+	crate::assert_eq!(1 + 1, 2);
+	crate::assert_ne!(1 + 1, 3);
+
+	let panic =
+		panic::catch_unwind(|| crate::assert_eq!(1 + 1, 3, "maths is {}", "broken")).unwrap_err();
+
+	assert_eq!(
+		panic.downcast_ref::<String>().map(String::as_str),
+		Some("assertion `left == right` failed: maths is broken\n  left: 2\n right: 3")
+	);
+}
+
+#[test]
+fn log_bytes() {
+	use crate::LoggerConfig;
+
+	let server = CaptureServer::start().unwrap();
+	let config = LoggerConfig::new().host(server.host()).port(server.port());
+
+	if let Err(e) = Logger::log_bytes_with_config(b"hello, world\n\0\x01", "text/plain", &config) {
+		panic!("{e}");
+	}
+
+	server.assert_logged(|log| {
+		log.message_type == "&[u8]"
+			&& log.message
+				== "00000000  68 65 6c 6c 6f 2c 20 77  6f 72 6c 64 0a 00 01     |hello, world...|\n15 bytes"
+	});
+}
+
+#[test]
+fn foreign_log() {
+	use crate::{foreign::ForeignLog, LoggerConfig};
+
+	let server = CaptureServer::start().unwrap();
+	let config = LoggerConfig::new().host(server.host()).port(server.port());
+
+	ForeignLog::new("C", "Connection refused")
+		.location("src/missing.c", 3)
+		.function("connect_to_server")
+		.snippet(2, "int connect_to_server(void) {\n\tlog_error();\n}")
+		.send_with_config(&config)
+		.unwrap();
+
+	server.assert_logged(|log| {
+		log.language == "C"
+			&& log.message == "Connection refused"
+			&& log.file_name == "src/missing.c"
+			&& log.line_number == 3
+			&& log.code_snippet.get(&3).map(String::as_str) == Some("\tlog_error();")
+			&& log.stack.len() == 1
+			&& log.stack[0].code == "log_error();"
+	});
+}
+
+#[test]
+fn environment_args() {
+	let args = [
+		"program",
+		"--token",
+		"abc",
+		"--password=hunter2",
+		"--verbose",
+		"input.txt",
+	];
+	let args = || args.iter().map(ToString::to_string);
+
+	assert_eq!(
+		crate::environment::args(args(), true),
+		[
+			"program",
+			"--token",
+			"<REDACTED>",
+			"--password=<REDACTED>",
+			"--verbose",
+			"input.txt"
+		]
+	);
+	assert_eq!(
+		crate::environment::args(args(), false),
+		args().collect::<Vec<_>>()
+	);
+}
+
+#[cfg(unix)]
+#[test]
+fn environment_not_unicode() {
+	use std::{collections::BTreeMap, ffi::OsStr, os::unix::ffi::OsStrExt};
+
+	let invalid = OsStr::from_bytes(b"caf\xe9");
+
+	assert_eq!(
+		crate::environment::args([OsStr::new("program"), invalid].into_iter(), true),
+		["program", "caf\u{FFFD}"]
+	);
+	assert_eq!(
+		crate::environment::variables(
+			[
+				(OsStr::new("RUST_LOG"), invalid),
+				(invalid, OsStr::new("value")),
+				(OsStr::new("HOME"), OsStr::new("/root")),
+			]
+			.into_iter(),
+			&["RUST_*".to_string()],
+			true,
+		),
+		BTreeMap::from([("RUST_LOG".to_string(), "caf\u{FFFD}".to_string())])
+	);
+}
+
+#[test]
+fn redaction() {
+	crate::redaction::add_pattern(r"redaction-test-secret-[0-9]+").unwrap();
+
+	let server = CaptureServer::start().unwrap();
+	let secret = "redaction-test-secret-1234";
+
+	if let Err(e) = Logger::log(
+		format!("The secret is {secret}"),
+		Some(2),
+		Some(server.host()),
+		Some(server.port()),
+		None,
+	) {
+		panic!("{e}");
+	}
+
+	server.assert_logged(|log| log.message.contains("The secret is <REDACTED>"));
+	server.assert_not_logged(|log| {
+		log.message.contains(secret)
+			|| log.code_snippet.values().any(|line| line.contains(secret))
+			|| log.stack.iter().any(|frame| frame.code.contains(secret))
+	});
+}
+
+#[test]
+fn redaction_of_bytes() {
+	// Distinct from the pattern of the `redaction` test, which runs in the same
+	// process.
+	crate::redaction::add_pattern(r"binary-secret-[0-9]+").unwrap();
+
+	let redacted = crate::redaction::redact_bytes(b"\0\x01binary-secret-42\xff");
+
+	assert_eq!(redacted.as_ref(), b"\0\x01<REDACTED>\xff");
+}
+
 // normal log
 fn log_layer_2() { log_layer_3(); }
 
 fn log_layer_3() { log_final_layer(); }
 
 fn log_final_layer() {
-	let a = || Logger::log("Hello", Some(2), None, None, None);
+	let server = CaptureServer::start().unwrap();
+
+	let a = || {
+		Logger::log(
+			"Hello",
+			Some(2),
+			Some(server.host()),
+			Some(server.port()),
+			None,
+		)
+	};
 	if let Err(e) = a() {
 		panic!("{e}");
 	}
+
+	server.assert_logged(|log| {
+		log.message.contains("Hello") && !log.stack.is_empty() && !log.uuid.is_empty()
+	});
+	server.assert_logged(|log| {
+		log.metadata.as_ref().is_some_and(|metadata| {
+			!metadata.session_id.is_empty()
+				&& !metadata.snippet_hash.is_empty()
+				&& metadata.file_hashes.contains_key(&log.file_name)
+				&& metadata.call.is_none()
+		})
+	});
 }
 
 // log_if
@@ -36,20 +534,28 @@ fn log_if_layer_2() { log_if_layer_3() }
 fn log_if_layer_3() { log_if_final_layer() }
 
 fn log_if_final_layer() {
+	let server = CaptureServer::start().unwrap();
 	let some_variable = true;
 
 	if let Err(e) = Logger::log_if(
 		log_if_gen_random,
 		"Hello, conditional",
 		Some(2),
-		None,
-		None,
+		Some(server.host()),
+		Some(server.port()),
 		None,
 	) {
 		panic!("{e}");
 	}
 
-	if let Err(e) = Logger::log_if(|| true, "Hello, conditional 2", Some(2), None, None, None) {
+	if let Err(e) = Logger::log_if(
+		|| true,
+		"Hello, conditional 2",
+		Some(2),
+		Some(server.host()),
+		Some(server.port()),
+		None,
+	) {
 		panic!("{e}");
 	}
 
@@ -57,12 +563,15 @@ fn log_if_final_layer() {
 		Box::new(move || some_variable),
 		"Hello, conditional 3",
 		Some(2),
-		None,
-		None,
+		Some(server.host()),
+		Some(server.port()),
 		None,
 	) {
 		panic!("{e}");
 	}
+
+	server.assert_logged(|log| log.message.contains("Hello, conditional 2"));
+	server.assert_logged(|log| log.message.contains("Hello, conditional 3"));
 }
 
 // log_when_env
@@ -71,8 +580,18 @@ fn log_when_env_layer_2() { log_when_env_layer_3() }
 fn log_when_env_layer_3() { log_when_env_final_layer() }
 
 fn log_when_env_final_layer() {
-	if let Err(e) = Logger::log_when_env("Hello, world env", Some(2), None, None, None) {
-		panic!("{e}");
+	let server = CaptureServer::start().unwrap();
+
+	match Logger::log_when_env(
+		"Hello, world env",
+		Some(2),
+		Some(server.host()),
+		Some(server.port()),
+		None,
+	) {
+		Ok(true) => server.assert_logged(|log| log.message.contains("Hello, world env")),
+		Ok(false) => server.assert_not_logged(|log| log.message.contains("Hello, world env")),
+		Err(e) => panic!("{e}"),
 	}
 }
 
@@ -81,7 +600,11 @@ fn log_batch_layer_2() { log_batch_layer_3() }
 fn log_batch_layer_3() {
 	use chrono::Utc;
 
+	let server = CaptureServer::start().unwrap();
+
 	let mut logger = Logger::start_batch()
+		.host(server.host())
+		.port(server.port())
 		.add_log(Utc::now(), None)
 		.add_log("Batched hello", None)
 		.add_log_if(|| true, "Batched hello conditional", None)
@@ -89,9 +612,25 @@ fn log_batch_layer_3() {
 		.add_log_when_env("Batched env", Some(2))
 		.build();
 
-	sleep(Duration::new(1, 0));
-
 	if let Err(e) = logger.send_batch() {
 		panic!("{e}");
 	}
+
+	server.assert_logged(|log| log.message.contains("Batched hello"));
+	server.assert_logged(|log| log.message.contains("Batched hello conditional"));
+	server.assert_not_logged(|log| log.message.contains("This won't show"));
+}
+
+#[cfg(feature = "semantic-snippets")]
+#[test]
+fn semantic_snippet() {
+	let source = include_str!("tests.rs");
+	let range = crate::semantic::enclosing_item(source, line!())
+		.expect("this test function should enclose its own line");
+
+	assert!(source
+		.lines()
+		.nth(*range.start() as usize - 1)
+		.unwrap()
+		.starts_with("#[cfg(feature"));
 }
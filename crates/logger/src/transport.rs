@@ -0,0 +1,278 @@
+//! Creates the gRPC channel used to send logs, over either plain text or TLS.
+
+use std::{error::Error, io, sync::Arc, time::SystemTime};
+
+use rustls::{
+	client::{ServerCertVerified, ServerCertVerifier},
+	ClientConfig, RootCertStore, ServerName,
+};
+use sha2::{Digest, Sha256};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tonic::{
+	transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Uri},
+	Code, Status,
+};
+
+use crate::{discovery, LoggerConfig, LoggerError, LoggerResult};
+
+/// TLS settings for connecting to a CodeCTRL server over `https://`. See
+/// [`LogBatch::tls`].
+///
+/// Without any certificates, the server's certificate is verified against the
+/// platform's trusted root certificates.
+///
+/// [`LogBatch::tls`]: crate::LogBatch::tls
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+	ca_certificate: Option<Vec<u8>>,
+	pinned_certificate: Option<Vec<u8>>,
+	domain_name: Option<String>,
+}
+
+impl TlsConfig {
+	pub fn new() -> Self { Self::default() }
+
+	/// Trusts the PEM-encoded CA certificate `pem` when verifying the server's
+	/// certificate, for servers using a certificate issued by a private CA.
+	///
+	/// A self-signed certificate can be given here as well. As self-signed
+	/// certificates are rarely issued for the host they are served from,
+	/// combine this with [`Self::domain_name`] to set the name the certificate
+	/// was issued for. Note that this trusts any certificate signed by `pem`,
+	/// not only `pem` itself; use [`Self::pinned_certificate`] for that.
+	pub fn ca_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+		self.ca_certificate = Some(pem.into());
+		self
+	}
+
+	/// Only accepts a server presenting exactly the PEM-encoded certificate
+	/// `pem`, such as its self-signed certificate, instead of verifying the
+	/// server's certificate against CA certificates. As the certificate is
+	/// compared as a whole, the domain name it was issued for isn't checked.
+	pub fn pinned_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+		self.pinned_certificate = Some(pem.into());
+		self
+	}
+
+	/// Overrides the domain name that the server's certificate is expected to
+	/// be issued for, which otherwise defaults to the host.
+	pub fn domain_name(mut self, domain_name: impl Into<String>) -> Self {
+		self.domain_name = Some(domain_name.into());
+		self
+	}
+
+	fn client_config(&self) -> ClientTlsConfig {
+		let mut config = ClientTlsConfig::new();
+
+		if let Some(pem) = &self.ca_certificate {
+			config = config.ca_certificate(Certificate::from_pem(pem));
+		}
+
+		if let Some(domain_name) = &self.domain_name {
+			config = config.domain_name(domain_name);
+		}
+
+		config
+	}
+}
+
+/// Connects to the server configured in `config`. TLS is used if a
+/// [`TlsConfig`] is given or if the host starts with `https://`.
+///
+/// If the address was found through the discovery file and the server can't be
+/// reached, the discovery file is read again in case the server has since been
+/// restarted elsewhere.
+pub(crate) async fn connect(config: &LoggerConfig) -> LoggerResult<Channel> {
+	match connect_to(config, &config.host, &config.port).await {
+		Err(error) if config.discover => match discovery::discover() {
+			Some((host, port)) if host != config.host || port.to_string() != config.port =>
+				connect_to(config, &host, &port.to_string()).await,
+			_ => Err(error),
+		},
+		result => result,
+	}
+}
+
+async fn connect_to(config: &LoggerConfig, host: &str, port: &str) -> LoggerResult<Channel> {
+	let LoggerConfig { timeout, tls, .. } = config;
+
+	let (scheme, host) = match host.split_once("://") {
+		Some((scheme, host)) => (scheme, host),
+		None if tls.is_some() => ("https", host),
+		None => ("http", host),
+	};
+
+	let pinned_certificate = tls
+		.as_ref()
+		.and_then(|tls| tls.pinned_certificate.as_ref())
+		.filter(|_| scheme == "https");
+
+	// A pinned certificate is checked by a TLS connection of our own, which
+	// tonic would otherwise wrap in a second one for an `https://` address.
+	let endpoint_scheme = match pinned_certificate {
+		Some(_) => "http",
+		None => scheme,
+	};
+
+	let mut endpoint = Endpoint::from_shared(format!("{endpoint_scheme}://{host}:{port}"))
+		.map_err(|error| LoggerError::LoggerError(format!("Invalid server address: {error}")))?;
+
+	if let Some(timeout) = *timeout {
+		endpoint = endpoint.timeout(timeout).connect_timeout(timeout);
+	}
+
+	if let Some(pem) = pinned_certificate {
+		let domain_name = tls.as_ref().and_then(|tls| tls.domain_name.as_deref());
+		let connector = pinned_connector(pem, domain_name)?;
+
+		return endpoint
+			.connect_with_connector(tower::service_fn(move |uri: Uri| {
+				let connector = connector.clone();
+
+				async move { connector.connect(uri).await }
+			}))
+			.await
+			.map_err(transport_error);
+	}
+
+	if endpoint_scheme == "https" {
+		let client_config = tls.clone().unwrap_or_default().client_config();
+
+		endpoint = endpoint
+			.tls_config(client_config)
+			.map_err(|error| LoggerError::TlsError(error.to_string()))?;
+	}
+
+	endpoint.connect().await.map_err(transport_error)
+}
+
+/// Makes the TLS connections to a server whose certificate is pinned with
+/// [`TlsConfig::pinned_certificate`].
+#[derive(Clone)]
+struct PinnedConnector {
+	connector: TlsConnector,
+	domain_name: Option<ServerName>,
+}
+
+impl PinnedConnector {
+	async fn connect(
+		&self,
+		uri: Uri,
+	) -> io::Result<tokio_rustls::client::TlsStream<TcpStream>> {
+		let host = uri.host().unwrap_or_default();
+		let port = uri.port_u16().unwrap_or(443);
+		// IPv6 addresses are kept in brackets in URIs.
+		let host = host.trim_start_matches('[').trim_end_matches(']');
+
+		let domain_name = match &self.domain_name {
+			Some(domain_name) => domain_name.clone(),
+			None => ServerName::try_from(host)
+				.map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?,
+		};
+
+		let stream = TcpStream::connect((host, port)).await?;
+		stream.set_nodelay(true)?;
+
+		self.connector.connect(domain_name, stream).await
+	}
+}
+
+fn pinned_connector(pem: &[u8], domain_name: Option<&str>) -> LoggerResult<PinnedConnector> {
+	let certificate = rustls_pemfile::certs(&mut &*pem)
+		.ok()
+		.and_then(|certificates| certificates.into_iter().next())
+		.ok_or_else(|| {
+			LoggerError::TlsError("The pinned certificate is not a PEM certificate".to_string())
+		})?;
+
+	let mut config = ClientConfig::builder()
+		.with_safe_defaults()
+		.with_root_certificates(RootCertStore::empty())
+		.with_no_client_auth();
+
+	config
+		.dangerous()
+		.set_certificate_verifier(Arc::new(PinnedVerifier {
+			sha256: Sha256::digest(certificate).to_vec(),
+		}));
+	// gRPC is only served over HTTP/2.
+	config.alpn_protocols = vec![b"h2".to_vec()];
+
+	let domain_name = domain_name
+		.map(ServerName::try_from)
+		.transpose()
+		.map_err(|error| LoggerError::TlsError(format!("Invalid domain name: {error}")))?;
+
+	Ok(PinnedConnector {
+		connector: TlsConnector::from(Arc::new(config)),
+		domain_name,
+	})
+}
+
+/// Accepts only the certificate with the SHA-256 digest `sha256`, regardless
+/// of who issued it or which name it was issued for.
+struct PinnedVerifier {
+	sha256: Vec<u8>,
+}
+
+impl ServerCertVerifier for PinnedVerifier {
+	fn verify_server_cert(
+		&self,
+		end_entity: &rustls::Certificate,
+		_intermediates: &[rustls::Certificate],
+		_server_name: &ServerName,
+		_scts: &mut dyn Iterator<Item = &[u8]>,
+		_ocsp_response: &[u8],
+		_now: SystemTime,
+	) -> Result<ServerCertVerified, rustls::Error> {
+		if Sha256::digest(&end_entity.0).as_slice() == self.sha256 {
+			Ok(ServerCertVerified::assertion())
+		} else {
+			Err(rustls::Error::InvalidCertificateData(
+				"The server's certificate doesn't match the pinned certificate".to_string(),
+			))
+		}
+	}
+}
+
+fn transport_error(error: tonic::transport::Error) -> LoggerError {
+	match certificate_error(&error) {
+		Some(certificate_error) => LoggerError::TlsError(certificate_error),
+		None => error.into(),
+	}
+}
+
+/// Whether a request that is `encoded_len` bytes long should be compressed.
+pub(crate) fn should_compress(config: &LoggerConfig, encoded_len: usize) -> bool {
+	matches!(config.compression_threshold, Some(threshold) if encoded_len >= threshold)
+}
+
+/// Whether `status` was returned because the server doesn't accept the
+/// compression that the request was sent with.
+pub(crate) fn compression_rejected(status: &Status) -> bool {
+	status.code() == Code::Unimplemented && status.message().contains("compressed with")
+}
+
+// rustls reports certificate errors wrapped inside of an `io::Error`, which is
+// itself buried inside of the errors from hyper and tonic.
+fn certificate_error(error: &(dyn Error + 'static)) -> Option<String> {
+	let mut source = Some(error);
+
+	while let Some(error) = source {
+		let rustls_error = error.downcast_ref::<rustls::Error>().or_else(|| {
+			error
+				.downcast_ref::<io::Error>()
+				.and_then(io::Error::get_ref)
+				.and_then(|inner| inner.downcast_ref::<rustls::Error>())
+		});
+
+		if let Some(rustls_error) = rustls_error {
+			return Some(rustls_error.to_string());
+		}
+
+		source = error.source();
+	}
+
+	None
+}
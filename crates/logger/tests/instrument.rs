@@ -0,0 +1,138 @@
+//! Tests of `#[instrument]`, which logs through the global `LoggerConfig`, so
+//! they are run in a process of their own that points it at a
+//! `CaptureServer`.
+
+use std::{num::ParseIntError, sync::OnceLock};
+
+use codectrl_logger::{
+	instrument,
+	testing::{CaptureServer, CapturedLog},
+	LoggerConfig,
+};
+use codectrl_protobuf_bindings::extensions::{CallEvent, CallMetadata};
+
+fn server() -> &'static CaptureServer {
+	static SERVER: OnceLock<CaptureServer> = OnceLock::new();
+
+	SERVER.get_or_init(|| {
+		let server = CaptureServer::start().unwrap();
+
+		LoggerConfig::new()
+			.host(server.host())
+			.port(server.port())
+			.set_global()
+			.unwrap();
+
+		server
+	})
+}
+
+// Returns the call metadata of the entry and exit logs of every call to
+// `function`, in the order they were received.
+fn calls(function: &str) -> Vec<(CapturedLog, CallMetadata)> {
+	server()
+		.logs()
+		.into_iter()
+		.filter_map(|log| {
+			let call = log.metadata.as_ref()?.call.clone()?;
+			(call.function == function).then_some((log, call))
+		})
+		.collect()
+}
+
+struct Stack {
+	values: Vec<u32>,
+}
+
+impl Stack {
+	// Returns a borrow with an elided lifetime.
+	#[instrument]
+	fn top(&self) -> Option<&u32> { self.values.last() }
+}
+
+#[instrument]
+fn add(a: u32, b: u32) -> u32 { a + b }
+
+#[instrument]
+fn clamp(value: u32) -> u32 {
+	if value > 10 {
+		return 10;
+	}
+
+	value
+}
+
+#[instrument]
+fn parse(text: &str) -> Result<u32, ParseIntError> {
+	let number = text.parse::<u32>()?;
+
+	Ok(number * 2)
+}
+
+#[instrument]
+fn first_even(values: &[u32]) -> Option<u32> {
+	let first = values.iter().find(|value| *value % 2 == 0)?;
+
+	Some(*first)
+}
+
+#[test]
+fn entry_and_exit_are_paired() {
+	server();
+	assert_eq!(add(1, 2), 3);
+
+	let calls = calls("add");
+	assert_eq!(calls.len(), 2);
+
+	let (entry_log, entry) = &calls[0];
+	let (exit_log, exit) = &calls[1];
+
+	assert_eq!(CallEvent::from_i32(entry.event), Some(CallEvent::Entry));
+	assert_eq!(CallEvent::from_i32(exit.event), Some(CallEvent::Exit));
+	assert_eq!(entry.call_id, exit.call_id);
+	assert_eq!(entry.paired_log_id, exit_log.uuid);
+	assert_eq!(exit.paired_log_id, entry_log.uuid);
+	assert!(entry.elapsed_nanos.is_none());
+	assert!(exit.elapsed_nanos.is_some());
+	assert!(entry_log.message.contains("a: 1") && entry_log.message.contains("b: 2"));
+	assert!(exit_log.message.contains("returned: 3"));
+}
+
+#[test]
+fn borrowed_return() {
+	server();
+
+	let stack = Stack { values: vec![1, 2] };
+	assert_eq!(stack.top(), Some(&2));
+
+	let calls = calls("top");
+	assert_eq!(calls.len(), 2);
+	assert_eq!(CallEvent::from_i32(calls[1].1.event), Some(CallEvent::Exit));
+	assert!(calls[1].0.message.contains("returned: Some("));
+}
+
+#[test]
+fn early_return() {
+	server();
+	assert_eq!(clamp(20), 10);
+
+	let calls = calls("clamp");
+	assert_eq!(calls.len(), 2);
+	assert_eq!(CallEvent::from_i32(calls[1].1.event), Some(CallEvent::Exit));
+	assert!(calls[1].0.message.contains("returned: 10"));
+}
+
+#[test]
+fn question_mark() {
+	server();
+	assert!(parse("not a number").is_err());
+	assert_eq!(first_even(&[1, 3]), None);
+
+	let parse_calls = calls("parse");
+	assert_eq!(parse_calls.len(), 2);
+	assert!(parse_calls[1].0.message.contains("returned: Err("));
+
+	let first_even_calls = calls("first_even");
+	assert_eq!(first_even_calls.len(), 2);
+	assert!(first_even_calls[1].0.message.contains("returned: None"));
+}
@@ -7,6 +7,7 @@ fn main() {
 	let path = std::env::current_dir().unwrap();
 	let current_dir = path.clone().as_os_str().to_string_lossy().to_string();
 	let proto_path = format!("{current_dir}/../protos/");
+	let extensions_path = format!("{current_dir}/../extensions/");
 	let path = Path::new(&proto_path);
 
 	if !path.exists() {
@@ -64,6 +65,34 @@ fn main() {
 			r#"#[derive(Serialize, Deserialize)]"#,
 		)
 		.type_attribute("codectrl.auth_service.Name", r#"#[repr(transparent)]"#)
+		.type_attribute(
+			"codectrl.extensions.GitMetadata",
+			r#"#[derive(Serialize, Deserialize)]"#,
+		)
+		.type_attribute(
+			"codectrl.extensions.LogMetadata",
+			r#"#[derive(Serialize, Deserialize)]"#,
+		)
+		.type_attribute(
+			"codectrl.extensions.CallMetadata",
+			r#"#[derive(Serialize, Deserialize)]"#,
+		)
+		.type_attribute(
+			"codectrl.extensions.TestMetadata",
+			r#"#[derive(Serialize, Deserialize)]"#,
+		)
+		.type_attribute(
+			"codectrl.extensions.BreakpointMetadata",
+			r#"#[derive(Serialize, Deserialize)]"#,
+		)
+		.type_attribute(
+			"codectrl.extensions.BinaryPayload",
+			r#"#[derive(Serialize, Deserialize)]"#,
+		)
+		.type_attribute(
+			"codectrl.extensions.EnvironmentSnapshot",
+			r#"#[derive(Serialize, Deserialize)]"#,
+		)
 		.build_server(!cfg!(target_arch = "wasm32"))
 		.compile_with_config(
 			config,
@@ -72,8 +101,9 @@ fn main() {
 				format!("{proto_path}/backtrace_data.proto"),
 				format!("{proto_path}/log.proto"),
 				format!("{proto_path}/auth.proto"),
+				format!("{extensions_path}/extensions.proto"),
 			],
-			&[format!("{proto_path}/")],
+			&[format!("{proto_path}/"), extensions_path.clone()],
 		)
 		.unwrap_or_else(|e| panic!("Failed to compile protos {e:#?}"));
 }
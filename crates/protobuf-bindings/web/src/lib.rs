@@ -52,3 +52,23 @@ pub mod auth_service {
 
 	tonic::include_proto!("codectrl.auth_service");
 }
+
+pub mod extensions {
+	use serde::{Deserialize, Serialize};
+
+	tonic::include_proto!("codectrl.extensions");
+
+	#[cfg(not(target_arch = "wasm32"))]
+	pub use extended_log_server_server::{
+		ExtendedLogServer as ExtendedLogServerTrait,
+		ExtendedLogServerServer as ExtendedLogServerService,
+	};
+
+	#[cfg(not(target_arch = "wasm32"))]
+	pub use extended_log_client_server::{
+		ExtendedLogClient as ExtendedLogClientTrait,
+		ExtendedLogClientServer as ExtendedLogClientService,
+	};
+
+	pub use extended_log_client_client::ExtendedLogClientClient as ExtendedLoggerClient;
+}
@@ -0,0 +1,70 @@
+//! The discovery file lets loggers on the same machine find a running server
+//! without being told its address, so that the server can be started on a
+//! non-default or random port.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+	fs,
+	net::SocketAddr,
+	path::{Path, PathBuf},
+	process,
+};
+
+/// The name of the discovery file inside the server's data directory.
+pub const DISCOVERY_FILE_NAME: &str = "server.json";
+
+/// The contents of the discovery file. Loggers read this to find the server,
+/// so any changes here must be mirrored in each logger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryFile {
+	pub host: String,
+	pub port: u16,
+	pub pid: u32,
+	pub requires_authentication: bool,
+}
+
+/// Removes the discovery file when dropped, i.e. when the server shuts down.
+#[derive(Debug)]
+pub(crate) struct DiscoveryGuard {
+	path: PathBuf,
+}
+
+impl DiscoveryGuard {
+	pub(crate) fn write(
+		data_dir: &Path,
+		address: SocketAddr,
+		requires_authentication: bool,
+	) -> anyhow::Result<Self> {
+		let path = data_dir.join(DISCOVERY_FILE_NAME);
+		let discovery = DiscoveryFile {
+			host: address.ip().to_string(),
+			port: address.port(),
+			pid: process::id(),
+			requires_authentication,
+		};
+
+		fs::write(&path, serde_json::to_string_pretty(&discovery)?)?;
+
+		info!("Wrote discovery file to {}", path.to_string_lossy());
+
+		Ok(Self { path })
+	}
+}
+
+impl Drop for DiscoveryGuard {
+	fn drop(&mut self) {
+		// Another server may have been started since, in which case the file is
+		// theirs to remove.
+		let is_ours = fs::read_to_string(&self.path)
+			.ok()
+			.and_then(|contents| serde_json::from_str::<DiscoveryFile>(&contents).ok())
+			.is_some_and(|discovery| discovery.pid == process::id());
+
+		if is_ours {
+			if let Err(error) = fs::remove_file(&self.path) {
+				warn!("Could not remove discovery file: {error}");
+			}
+		}
+	}
+}
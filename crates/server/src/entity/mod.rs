@@ -4,4 +4,7 @@
 
 pub mod prelude;
 
+pub mod backtrace_data;
+pub mod code_snippet;
 pub mod connection;
+pub mod log;
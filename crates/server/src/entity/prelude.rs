@@ -2,4 +2,7 @@
 
 //! SeaORM Entity. Generated by sea-orm-codegen 0.9.1
 
-pub use super::connection::Entity as Connection;
+pub use super::{
+	backtrace_data::Entity as BacktraceData, code_snippet::Entity as CodeSnippet,
+	connection::Entity as Connection, log::Entity as Log,
+};
@@ -1,8 +1,16 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::invalid_regex, clippy::too_many_lines)]
 
+mod discovery;
 mod entity;
+mod log_store;
+mod migration;
 pub mod redirect_handler;
+mod retention;
+#[cfg(test)]
+mod tests;
+
+pub use discovery::{DiscoveryFile, DISCOVERY_FILE_NAME};
 
 // region: imports
 
@@ -13,17 +21,25 @@ use codectrl_protobuf_bindings::{
 		VerifyTokenRequest, VerifyTokenRequestResult,
 	},
 	data::Log,
+	extensions::{
+		CallSite, CallSiteRule, CallSiteRules, CallSiteStatus, CallSites, EnvironmentSnapshot,
+		EvictedLogs, ExtendedLog, ExtendedLogClientService, ExtendedLogClientTrait,
+		ExtendedLogServerService, ExtendedLogServerTrait, LogAck, LogId, LogMetadata, LogPin, TestCase,
+		TestCases, TestOutcome, TestResult,
+	},
 	logs_service::{
 		Connection, LogClientService, LogClientTrait, LogServerService, LogServerTrait, RequestResult,
 		RequestStatus, ServerDetails,
 	},
 };
-use dashmap::{DashMap, DashSet};
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
 use directories::ProjectDirs;
+use discovery::DiscoveryGuard;
 use dotenv::dotenv;
 use entity::connection::{ActiveModel, Entity};
 use futures::StreamExt;
 use log::{error, info, trace, warn};
+use migration::Migrator;
 use oauth2::{
 	basic::BasicClient, AuthUrl, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope, TokenUrl,
 };
@@ -33,26 +49,31 @@ use rand::{
 	thread_rng,
 };
 use regex::Regex;
+use retention::{RetainedLog, RetentionPolicy};
 use sea_orm::{
-	ActiveModelTrait, ActiveValue::NotSet, ConnectionTrait, Database, DatabaseConnection,
-	EntityTrait, Schema, Set,
+	ActiveModelTrait, ActiveValue::NotSet, Database, DatabaseConnection, EntityTrait, Set,
 };
+use sea_orm_migration::MigratorTrait;
 use serde::{Deserialize, Serialize};
 use std::{
 	borrow::Cow,
-	collections::VecDeque,
+	collections::{HashMap, HashSet, VecDeque},
 	env,
 	fs::{self, File},
 	net::SocketAddr,
 	path::Path,
 	sync::Arc,
-	time::{Duration, Instant},
+	time::{Duration, Instant, SystemTime},
 };
 use tokio::{
-	sync::{mpsc, RwLock},
+	net::TcpListener,
+	sync::{
+		broadcast::{self, error::RecvError},
+		mpsc, Notify, RwLock,
+	},
 	time::sleep_until,
 };
-use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
 use tonic::{metadata::MetadataMap, transport::Server, Code, Request, Response, Status, Streaming};
 use uuid::Uuid;
 // use warp::filters::host;
@@ -66,6 +87,20 @@ static CENSOR_USERNAMES: OnceBool = OnceBool::new();
 // definitely be faster.
 static USERNAME_REGEXES: OnceCell<[Result<Regex, regex::Error>; 4]> = OnceCell::new();
 static REDIRECT_HANDLER_PORT: OnceCell<u16> = OnceCell::new();
+// How long the IDs of received logs are remembered for, so that a log resent by
+// a client retrying after a timeout is only stored once.
+const DEDUPLICATION_WINDOW: Duration = Duration::from_secs(5 * 60);
+// How many acknowledgements from `stream_extended_logs` can wait to be read by
+// the client. Once full, no more logs are read from the client until it catches
+// up, which applies backpressure to it through HTTP/2 flow control.
+const ACK_BUFFER_SIZE: usize = 256;
+// How many changes to call site rules can wait to be sent to each watching
+// client. A client that falls further behind is sent every rule again.
+const RULE_UPDATE_BUFFER_SIZE: usize = 64;
+// How many evictions can wait to be sent to each watching GUI.
+const EVICTION_BUFFER_SIZE: usize = 64;
+// How often logs over the retention limits are evicted.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(10);
 
 // region: ConnectionState
 #[derive(Debug, Clone)]
@@ -91,10 +126,40 @@ impl ConnectionState {
 }
 // endregion
 
+// region: PausedBreakpoint
+// A program waiting in `wait_for_resume` for a breakpoint to be resumed. The
+// breakpoint is paused as soon as its log is received, so that it can be
+// resumed before the program starts waiting, and stops being paused once
+// resumed, or when the program stops waiting for it and the request is dropped.
+struct PausedBreakpoint<'a> {
+	paused_breakpoints: &'a DashMap<String, Arc<Notify>>,
+	uuid: String,
+}
+
+impl Drop for PausedBreakpoint<'_> {
+	fn drop(&mut self) { self.paused_breakpoints.remove(&self.uuid); }
+}
+// endregion
+
 // region: Service
 #[derive(Debug, Clone)]
 pub struct Service {
 	logs: Arc<RwLock<VecDeque<Log>>>,
+	log_metadata: Arc<DashMap<String, LogMetadata>>,
+	received_log_ids: Arc<DashMap<String, Instant>>,
+	// The outcome of each test, keyed by the test's run ID and name.
+	test_results: Arc<DashMap<(String, String), i32>>,
+	call_sites: Arc<DashMap<String, CallSite>>,
+	call_site_rules: Arc<DashMap<String, CallSiteRule>>,
+	call_site_rule_updates: broadcast::Sender<CallSiteRule>,
+	// Notified to resume the breakpoint log with the given ID.
+	paused_breakpoints: Arc<DashMap<String, Arc<Notify>>>,
+	// The environment that each logging process was started in, keyed by its
+	// session ID.
+	environments: Arc<DashMap<String, EnvironmentSnapshot>>,
+	retention_policy: RetentionPolicy,
+	retained_logs: Arc<DashMap<String, RetainedLog>>,
+	evicted_logs: broadcast::Sender<EvictedLogs>,
 	connections: Arc<RwLock<DashMap<String, ConnectionState>>>,
 	host: String,
 	port: u32,
@@ -107,6 +172,7 @@ impl Service {
 	pub fn start_backup_thread(&self) {
 		let connections = Arc::clone(&self.connections);
 		let db_connection = Arc::clone(&self.db_connection);
+		let received_log_ids = Arc::clone(&self.received_log_ids);
 
 		info!("Starting background backup thread...");
 
@@ -115,6 +181,8 @@ impl Service {
 			loop {
 				sleep_until(tokio::time::Instant::now() + Duration::new(5, 0)).await;
 
+				received_log_ids.retain(|_, received| received.elapsed() < DEDUPLICATION_WINDOW);
+
 				for mut connection in connections.write().await.iter_mut() {
 					if connection.last_update.elapsed() >= Duration::new(5, 0) {
 						let sent_logs =
@@ -143,6 +211,82 @@ impl Service {
 		info!("... Done!");
 	}
 
+	pub fn start_retention_thread(&self) {
+		let service = self.clone();
+
+		info!(
+			"Starting background retention thread with {:?}...",
+			self.retention_policy
+		);
+
+		tokio::spawn(async move {
+			loop {
+				sleep_until(tokio::time::Instant::now() + RETENTION_INTERVAL).await;
+
+				service.evict_logs().await;
+			}
+		});
+
+		info!("... Done!");
+	}
+
+	// Evicts the logs that are over the retention limits from memory and from the
+	// database, and tells the watching GUIs which logs were evicted.
+	async fn evict_logs(&self) {
+		let mut logs = self.logs.write().await;
+
+		let retained = logs
+			.iter()
+			.filter_map(|log| {
+				let retained = self.retained_logs.get(&log.uuid)?;
+				Some((log.uuid.clone(), retained.clone()))
+			})
+			.collect::<Vec<_>>();
+
+		let evicted = retention::evictions(&self.retention_policy, &retained, SystemTime::now());
+
+		if evicted.is_empty() {
+			return;
+		}
+
+		logs.retain(|log| !evicted.contains(&log.uuid));
+		drop(logs);
+
+		for uuid in &evicted {
+			self.retained_logs.remove(uuid);
+			self.log_metadata.remove(uuid);
+			self.paused_breakpoints.remove(uuid);
+		}
+
+		// The environment of a session is kept for as long as it has logs.
+		let sessions = self
+			.retained_logs
+			.iter()
+			.map(|log| log.session.clone())
+			.collect::<HashSet<_>>();
+
+		self
+			.environments
+			.retain(|session_id, _| sessions.contains(session_id));
+
+		for connection in self.connections.read().await.iter() {
+			connection
+				.sent_log_ids
+				.retain(|uuid| !evicted.contains(uuid));
+		}
+
+		let uuids = evicted.into_iter().collect::<Vec<_>>();
+
+		if let Err(error) = log_store::delete(&self.db_connection, uuids.clone()).await {
+			error!("Could not delete evicted logs: {error}");
+		}
+
+		info!("Evicted {} log(s) over the retention limits", uuids.len());
+
+		// There may not be any GUIs watching.
+		self.evicted_logs.send(EvictedLogs { uuids }).ok();
+	}
+
 	fn strip_username_from_path(path: &str) -> Cow<str> {
 		let path: Cow<str> = path.into();
 
@@ -181,7 +325,11 @@ impl Service {
 
 	#[allow(clippy::missing_panics_doc)]
 	pub fn verify_log(log: &mut Log, remote_addr: Option<SocketAddr>, metadata: &MetadataMap) {
-		log.uuid = Uuid::new_v4().hyphenated().to_string();
+		// Loggers generate the ID of each log so that a resent log can be
+		// recognised, but anything that isn't a valid UUID is replaced.
+		if !matches!(Uuid::try_parse(&log.uuid), Ok(uuid) if !uuid.is_nil()) {
+			log.uuid = Uuid::new_v4().hyphenated().to_string();
+		}
 
 		if log.message.len() > 1000 {
 			log.warnings.push("Message exceeds 1000 characters".into());
@@ -232,6 +380,186 @@ impl Service {
 		}
 	}
 
+	/// Censors usernames in the file paths that `metadata` refers to, including
+	/// the current directory and arguments of its environment, in the same way
+	/// that [`Self::verify_log`] does for the log itself.
+	pub fn verify_metadata(metadata: &mut LogMetadata) {
+		if let Some(censor_usernames) = CENSOR_USERNAMES.get() {
+			if censor_usernames {
+				metadata.file_hashes = std::mem::take(&mut metadata.file_hashes)
+					.into_iter()
+					.map(|(path, hash)| (Self::strip_username_from_path(&path).to_string(), hash))
+					.collect();
+
+				if let Some(environment) = metadata.environment.as_mut() {
+					environment.cwd = Self::strip_username_from_path(&environment.cwd).to_string();
+
+					for arg in &mut environment.args {
+						*arg = Self::strip_username_from_path(arg).to_string();
+					}
+				}
+			}
+		}
+	}
+
+	// Saves `log` and its metadata to the database, then stores the metadata.
+	// Failing to save the log doesn't fail the request, as it is still kept in
+	// memory until the server stops.
+	async fn store_log(&self, log: &Log, metadata: Option<LogMetadata>) {
+		let metadata = metadata.map(|mut metadata| {
+			Self::verify_metadata(&mut metadata);
+			metadata
+		});
+		let received_at = SystemTime::now();
+
+		if let Err(error) =
+			log_store::save(&self.db_connection, log, metadata.as_ref(), received_at).await
+		{
+			error!("Could not save log {}: {error}", log.uuid);
+		}
+
+		self.retain_log(log, metadata, received_at);
+	}
+
+	// Saves every log of a batch to the database in a single transaction, in the
+	// same way as `store_log`, and returns the logs to be added to `self.logs`.
+	async fn store_logs(&self, logs: Vec<(Log, Option<LogMetadata>)>) -> Vec<Log> {
+		let logs = logs
+			.into_iter()
+			.map(|(log, metadata)| {
+				let metadata = metadata.map(|mut metadata| {
+					Self::verify_metadata(&mut metadata);
+					metadata
+				});
+
+				(log, metadata)
+			})
+			.collect::<Vec<_>>();
+		let received_at = SystemTime::now();
+
+		if let Err(error) = log_store::save_all(&self.db_connection, &logs, received_at).await {
+			error!("Could not save a batch of {} logs: {error}", logs.len());
+		}
+
+		logs
+			.into_iter()
+			.map(|(log, metadata)| {
+				self.retain_log(&log, metadata, received_at);
+				log
+			})
+			.collect()
+	}
+
+	fn retain_log(&self, log: &Log, metadata: Option<LogMetadata>, received_at: SystemTime) {
+		if metadata
+			.as_ref()
+			.is_some_and(|metadata| metadata.breakpoint.is_some())
+		{
+			self
+				.paused_breakpoints
+				.entry(log.uuid.clone())
+				.or_default();
+		}
+
+		self.retained_logs.insert(
+			log.uuid.clone(),
+			RetainedLog::new(log, metadata.as_ref(), received_at, false),
+		);
+
+		if let Some(metadata) = metadata {
+			self.store_metadata(&log.uuid, metadata);
+		}
+	}
+
+	// Stores the metadata of the log with the ID `uuid`. The environment is only
+	// sent with the first log of each session, so it is stored for the session
+	// rather than the log.
+	fn store_metadata(&self, uuid: &str, mut metadata: LogMetadata) {
+		if let Some(environment) = metadata.environment.take() {
+			self
+				.environments
+				.insert(metadata.session_id.clone(), environment);
+		}
+
+		self.log_metadata.insert(uuid.to_string(), metadata);
+	}
+
+	/// Records that the log with the ID `uuid` has been received. Returns
+	/// `false` if it was already received within the deduplication window, in
+	/// which case it is a retry and shouldn't be stored again.
+	fn first_delivery(&self, uuid: &str) -> bool {
+		match self.received_log_ids.entry(uuid.to_string()) {
+			Entry::Occupied(entry) if entry.get().elapsed() < DEDUPLICATION_WINDOW => false,
+			Entry::Occupied(mut entry) => {
+				entry.insert(Instant::now());
+				true
+			},
+			Entry::Vacant(entry) => {
+				entry.insert(Instant::now());
+				true
+			},
+		}
+	}
+
+	// Stores a single log received through `stream_extended_logs`, returning the
+	// acknowledgement to send back for it.
+	async fn receive_streamed_log(
+		&self,
+		extended_log: ExtendedLog,
+		remote_addr: Option<SocketAddr>,
+		metadata: &MetadataMap,
+	) -> LogAck {
+		let ExtendedLog {
+			log,
+			metadata: log_metadata,
+		} = extended_log;
+
+		let Some(mut log) = log else {
+			return LogAck {
+				uuid: String::new(),
+				status: RequestStatus::Error.into(),
+				message: "No log was supplied".into(),
+			};
+		};
+
+		// Acknowledged with the ID the logger knows it by, even if it had to be
+		// replaced.
+		let ack = LogAck {
+			uuid: log.uuid.clone(),
+			status: RequestStatus::Confirmed.into(),
+			message: String::new(),
+		};
+
+		Self::verify_log(&mut log, remote_addr, metadata);
+
+		if !self.first_delivery(&log.uuid) {
+			return LogAck {
+				message: "Log already received".into(),
+				..ack
+			};
+		}
+
+		if option_env!("LOG_PRINT").is_some() {
+			dbg!(&log, &log_metadata);
+		}
+
+		self.store_log(&log, log_metadata).await;
+
+		self.logs.write().await.push_back(log);
+
+		ack
+	}
+
+	fn call_site_rules(&self) -> CallSiteRules {
+		CallSiteRules {
+			rules: self
+				.call_site_rules
+				.iter()
+				.map(|rule| rule.value().clone())
+				.collect(),
+		}
+	}
+
 	pub fn requires_authentication(&mut self, requires_authentication: bool) {
 		self.requires_authentication = requires_authentication;
 	}
@@ -467,10 +795,25 @@ impl LogClientTrait for Service {
 
 		Self::verify_log(&mut log, remote_addr, &metadata);
 
+		if !self.first_delivery(&log.uuid) {
+			info!(
+				"Duplicate log {} ignored from {}",
+				log.uuid,
+				remote_addr.unwrap()
+			);
+
+			return Ok(Response::new(RequestResult {
+				message: "Log already received!".into(),
+				status: RequestStatus::Confirmed.into(),
+				auth_status: None,
+			}));
+		}
+
 		if option_env!("LOG_PRINT").is_some() {
 			dbg!(&log);
 		}
 
+		self.store_log(&log, None).await;
 		self.logs.write().await.push_back(log);
 
 		info!("Log received from {}", remote_addr.unwrap());
@@ -490,9 +833,8 @@ impl LogClientTrait for Service {
 		let metadata = request.metadata().clone();
 		let mut stream = request.into_inner();
 
-		let mut lock = self.logs.write().await;
-
-		let mut amount = 0;
+		let mut received = Vec::new();
+		let mut duplicates = 0;
 		while let Some(log) = stream.next().await {
 			let mut log = log?;
 
@@ -501,21 +843,554 @@ impl LogClientTrait for Service {
 			}
 
 			Self::verify_log(&mut log, remote_addr, &metadata);
-			lock.push_back(log);
 
-			amount += 1;
+			if !self.first_delivery(&log.uuid) {
+				duplicates += 1;
+				continue;
+			}
+
+			received.push((log, None));
+		}
+
+		// The logs are only locked once the batch is saved, so that the GUI
+		// isn't blocked while the database is written to.
+		let amount = received.len();
+		let stored = self.store_logs(received).await;
+		self.logs.write().await.extend(stored);
+
+		info!(
+			"{amount} log(s) received from {}, {duplicates} duplicate(s) ignored",
+			remote_addr.unwrap()
+		);
+
+		Ok(Response::new(RequestResult {
+			message: added_message(amount, duplicates),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+}
+
+fn added_message(amount: usize, duplicates: usize) -> String {
+	if duplicates == 0 {
+		format!("{amount} logs added!")
+	} else {
+		format!("{amount} logs added! {duplicates} already received logs were ignored.")
+	}
+}
+
+// endregion
+// region: extensions implementation
+
+#[tonic::async_trait]
+impl ExtendedLogClientTrait for Service {
+	async fn send_extended_log(
+		&self,
+		request: Request<ExtendedLog>,
+	) -> Result<Response<RequestResult>, Status> {
+		let remote_addr = request.remote_addr();
+		let metadata = request.metadata().clone();
+		let ExtendedLog {
+			log,
+			metadata: log_metadata,
+		} = request.into_inner();
+
+		let Some(mut log) = log else {
+			return Err(Status::invalid_argument("No log was supplied"));
+		};
+
+		Self::verify_log(&mut log, remote_addr, &metadata);
+
+		if !self.first_delivery(&log.uuid) {
+			info!(
+				"Duplicate extended log {} ignored from {}",
+				log.uuid,
+				remote_addr.unwrap()
+			);
+
+			return Ok(Response::new(RequestResult {
+				message: "Log already received!".into(),
+				status: RequestStatus::Confirmed.into(),
+				auth_status: None,
+			}));
+		}
+
+		if option_env!("LOG_PRINT").is_some() {
+			dbg!(&log, &log_metadata);
+		}
+
+		self.store_log(&log, log_metadata).await;
+
+		self.logs.write().await.push_back(log);
+
+		info!("Extended log received from {}", remote_addr.unwrap());
+
+		Ok(Response::new(RequestResult {
+			message: "Log added!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	async fn send_extended_logs(
+		&self,
+		request: Request<Streaming<ExtendedLog>>,
+	) -> Result<Response<RequestResult>, Status> {
+		let remote_addr = request.remote_addr();
+		let metadata = request.metadata().clone();
+		let mut stream = request.into_inner();
+
+		let mut received = Vec::new();
+		let mut duplicates = 0;
+		while let Some(extended_log) = stream.next().await {
+			let ExtendedLog {
+				log,
+				metadata: log_metadata,
+			} = extended_log?;
+
+			let Some(mut log) = log else {
+				continue;
+			};
+
+			if option_env!("LOG_PRINT").is_some() {
+				dbg!(&log, &log_metadata);
+			}
+
+			Self::verify_log(&mut log, remote_addr, &metadata);
+
+			if !self.first_delivery(&log.uuid) {
+				duplicates += 1;
+				continue;
+			}
+
+			received.push((log, log_metadata));
+		}
+
+		let amount = received.len();
+		let stored = self.store_logs(received).await;
+		self.logs.write().await.extend(stored);
+
+		info!(
+			"{amount} extended log(s) received from {}, {duplicates} duplicate(s) ignored",
+			remote_addr.unwrap()
+		);
+
+		Ok(Response::new(RequestResult {
+			message: added_message(amount, duplicates),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	type StreamExtendedLogsStream = ReceiverStream<Result<LogAck, Status>>;
+
+	async fn stream_extended_logs(
+		&self,
+		request: Request<Streaming<ExtendedLog>>,
+	) -> Result<Response<Self::StreamExtendedLogsStream>, Status> {
+		let remote_addr = request.remote_addr();
+		let metadata = request.metadata().clone();
+		let mut stream = request.into_inner();
+		let (tx, rx) = mpsc::channel(ACK_BUFFER_SIZE);
+		let service = self.clone();
+
+		tokio::spawn(async move {
+			let mut amount = 0;
+
+			while let Some(extended_log) = stream.next().await {
+				let extended_log = match extended_log {
+					Ok(extended_log) => extended_log,
+					Err(status) => {
+						warn!(
+							"Log stream from {} ended with an error: {status}",
+							remote_addr.unwrap()
+						);
+						break;
+					},
+				};
+
+				let ack = service
+					.receive_streamed_log(extended_log, remote_addr, &metadata)
+					.await;
+
+				if ack.status == i32::from(RequestStatus::Confirmed) {
+					amount += 1;
+				}
+
+				if tx.send(Ok(ack)).await.is_err() {
+					break;
+				}
+			}
+
+			info!(
+				"{amount} streamed log(s) received from {}",
+				remote_addr.unwrap()
+			);
+		});
+
+		Ok(Response::new(ReceiverStream::new(rx)))
+	}
+
+	async fn register_call_site(
+		&self,
+		request: Request<CallSite>,
+	) -> Result<Response<RequestResult>, Status> {
+		let mut call_site = request.into_inner();
+
+		if call_site.id.is_empty() {
+			return Err(Status::invalid_argument("No call site ID was supplied"));
+		}
+
+		if let Some(censor_usernames) = CENSOR_USERNAMES.get() {
+			if censor_usernames {
+				call_site.file_path = Self::strip_username_from_path(&call_site.file_path).to_string();
+			}
 		}
 
-		info!("{amount} log(s) received from {}", remote_addr.unwrap());
+		trace!(
+			"Call site {} registered at {}:{}",
+			call_site.id,
+			call_site.file_path,
+			call_site.line_number
+		);
+
+		self.call_sites.insert(call_site.id.clone(), call_site);
 
 		Ok(Response::new(RequestResult {
-			message: format!("{amount} logs added!"),
+			message: "Call site registered!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	type WatchCallSiteRulesStream = ReceiverStream<Result<CallSiteRules, Status>>;
+
+	async fn watch_call_site_rules(
+		&self,
+		request: Request<()>,
+	) -> Result<Response<Self::WatchCallSiteRulesStream>, Status> {
+		let remote_addr = request.remote_addr();
+		let (tx, rx) = mpsc::channel(RULE_UPDATE_BUFFER_SIZE);
+		// Subscribed to before taking every rule, so that no change is missed in
+		// between.
+		let mut updates = self.call_site_rule_updates.subscribe();
+		let service = self.clone();
+
+		tokio::spawn(async move {
+			let mut rules = service.call_site_rules();
+
+			loop {
+				if tx.send(Ok(rules)).await.is_err() {
+					break;
+				}
+
+				rules = tokio::select! {
+					update = updates.recv() => match update {
+						Ok(rule) => CallSiteRules { rules: vec![rule] },
+						Err(RecvError::Lagged(_)) => service.call_site_rules(),
+						Err(RecvError::Closed) => break,
+					},
+					// The client disconnected.
+					() = tx.closed() => break,
+				};
+			}
+
+			trace!(
+				"Stopped sending call site rules to {}",
+				remote_addr.unwrap()
+			);
+		});
+
+		Ok(Response::new(ReceiverStream::new(rx)))
+	}
+
+	async fn wait_for_resume(
+		&self,
+		request: Request<LogId>,
+	) -> Result<Response<RequestResult>, Status> {
+		let remote_addr = request.remote_addr();
+		let LogId { uuid } = request.into_inner();
+
+		let is_breakpoint = self
+			.log_metadata
+			.get(&uuid)
+			.is_some_and(|metadata| metadata.breakpoint.is_some());
+
+		if !is_breakpoint {
+			return Err(Status::not_found(
+				"No breakpoint was found for the given log",
+			));
+		}
+
+		// Holds a permit if the breakpoint was already resumed.
+		let resumed = Arc::clone(&self.paused_breakpoints.entry(uuid.clone()).or_default());
+
+		let _paused = PausedBreakpoint {
+			paused_breakpoints: &self.paused_breakpoints,
+			uuid: uuid.clone(),
+		};
+
+		info!("Breakpoint {uuid} paused from {}", remote_addr.unwrap());
+
+		resumed.notified().await;
+
+		Ok(Response::new(RequestResult {
+			message: "Breakpoint resumed!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	async fn report_test_result(
+		&self,
+		request: Request<TestResult>,
+	) -> Result<Response<RequestResult>, Status> {
+		let remote_addr = request.remote_addr();
+		let TestResult {
+			run_id,
+			name,
+			outcome,
+		} = request.into_inner();
+
+		let Some(test_outcome) = TestOutcome::from_i32(outcome) else {
+			return Err(Status::invalid_argument("Unknown test outcome"));
+		};
+
+		info!(
+			"Test {name} reported as {test_outcome:?} from {}",
+			remote_addr.unwrap()
+		);
+
+		self.test_results.insert((run_id, name), outcome);
+
+		Ok(Response::new(RequestResult {
+			message: "Test result recorded!".into(),
 			status: RequestStatus::Confirmed.into(),
 			auth_status: None,
 		}))
 	}
 }
 
+#[tonic::async_trait]
+impl ExtendedLogServerTrait for Service {
+	async fn get_log_metadata(
+		&self,
+		request: Request<LogId>,
+	) -> Result<Response<LogMetadata>, Status> {
+		let LogId { uuid } = request.into_inner();
+
+		// Logs sent without metadata can still be pinned, so they are given empty
+		// metadata to carry whether they are.
+		let mut metadata = match self.log_metadata.get(&uuid) {
+			Some(metadata) => metadata.clone(),
+			None if self.retained_logs.contains_key(&uuid) => LogMetadata::default(),
+			None => return Err(Status::not_found("No log was found with the given ID")),
+		};
+
+		metadata.environment = self
+			.environments
+			.get(&metadata.session_id)
+			.map(|environment| environment.clone());
+		metadata.pinned = self.retained_logs.get(&uuid).is_some_and(|log| log.pinned);
+
+		Ok(Response::new(metadata))
+	}
+
+	async fn get_test_cases(&self, _: Request<()>) -> Result<Response<TestCases>, Status> {
+		let mut test_cases: Vec<TestCase> = vec![];
+		let mut indices = HashMap::new();
+
+		for log in self.logs.read().await.iter() {
+			let Some(test) = self
+				.log_metadata
+				.get(&log.uuid)
+				.and_then(|metadata| metadata.test.clone())
+			else {
+				continue;
+			};
+
+			let index = *indices
+				.entry((test.run_id.clone(), test.name.clone()))
+				.or_insert_with(|| {
+					test_cases.push(TestCase {
+						run_id: test.run_id,
+						name: test.name,
+						outcome: None,
+						log_ids: vec![],
+					});
+
+					test_cases.len() - 1
+				});
+
+			test_cases[index].log_ids.push(log.uuid.clone());
+		}
+
+		// Tests can fail before creating any logs.
+		for result in self.test_results.iter() {
+			let (run_id, name) = result.key();
+
+			match indices.get(result.key()) {
+				Some(&index) => test_cases[index].outcome = Some(*result.value()),
+				None => test_cases.push(TestCase {
+					run_id: run_id.clone(),
+					name: name.clone(),
+					outcome: Some(*result.value()),
+					log_ids: vec![],
+				}),
+			}
+		}
+
+		Ok(Response::new(TestCases { test_cases }))
+	}
+
+	async fn get_call_sites(&self, _: Request<()>) -> Result<Response<CallSites>, Status> {
+		let mut call_sites = self
+			.call_sites
+			.iter()
+			.map(|call_site| CallSiteStatus {
+				call_site: Some(call_site.value().clone()),
+				rule: self
+					.call_site_rules
+					.get(call_site.key())
+					.map(|rule| rule.value().clone()),
+			})
+			.collect::<Vec<_>>();
+
+		call_sites.sort_by(|a, b| {
+			let location = |status: &CallSiteStatus| {
+				status
+					.call_site
+					.as_ref()
+					.map(|call_site| (call_site.file_path.clone(), call_site.line_number))
+			};
+
+			location(a).cmp(&location(b))
+		});
+
+		Ok(Response::new(CallSites { call_sites }))
+	}
+
+	async fn resume_breakpoint(
+		&self,
+		request: Request<LogId>,
+	) -> Result<Response<RequestResult>, Status> {
+		let LogId { uuid } = request.into_inner();
+
+		let Some(resumed) = self.paused_breakpoints.get(&uuid) else {
+			return Err(Status::failed_precondition(
+				"The program is no longer paused at this breakpoint",
+			));
+		};
+
+		// Stores a permit if the program isn't waiting yet, so the resume isn't
+		// lost.
+		resumed.notify_one();
+
+		info!("Breakpoint {uuid} resumed");
+
+		Ok(Response::new(RequestResult {
+			message: "Breakpoint resumed!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	async fn set_call_site_rule(
+		&self,
+		request: Request<CallSiteRule>,
+	) -> Result<Response<RequestResult>, Status> {
+		let rule = request.into_inner();
+
+		if rule.call_site_id.is_empty() {
+			return Err(Status::invalid_argument("No call site ID was supplied"));
+		}
+
+		info!(
+			"Call site {} {}",
+			rule.call_site_id,
+			if rule.enabled { "enabled" } else { "disabled" }
+		);
+
+		self
+			.call_site_rules
+			.insert(rule.call_site_id.clone(), rule.clone());
+
+		// There may not be any running programs to send the rule to.
+		self.call_site_rule_updates.send(rule).ok();
+
+		Ok(Response::new(RequestResult {
+			message: "Call site rule set!".into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	async fn set_log_pinned(
+		&self,
+		request: Request<LogPin>,
+	) -> Result<Response<RequestResult>, Status> {
+		let LogPin { uuid, pinned } = request.into_inner();
+
+		let Some(mut log) = self.retained_logs.get_mut(&uuid) else {
+			return Err(Status::not_found("No log was found with the given ID"));
+		};
+
+		log.pinned = pinned;
+		drop(log);
+
+		if let Err(error) = log_store::set_pinned(&self.db_connection, &uuid, pinned).await {
+			error!("Could not save whether log {uuid} is pinned: {error}");
+		}
+
+		info!("Log {uuid} {}", if pinned { "pinned" } else { "unpinned" });
+
+		Ok(Response::new(RequestResult {
+			message: if pinned {
+				"Log pinned!"
+			} else {
+				"Log unpinned!"
+			}
+			.into(),
+			status: RequestStatus::Confirmed.into(),
+			auth_status: None,
+		}))
+	}
+
+	type WatchEvictedLogsStream = ReceiverStream<Result<EvictedLogs, Status>>;
+
+	async fn watch_evicted_logs(
+		&self,
+		request: Request<()>,
+	) -> Result<Response<Self::WatchEvictedLogsStream>, Status> {
+		let remote_addr = request.remote_addr();
+		let (tx, rx) = mpsc::channel(EVICTION_BUFFER_SIZE);
+		let mut evictions = self.evicted_logs.subscribe();
+
+		tokio::spawn(async move {
+			loop {
+				let evicted = tokio::select! {
+					evicted = evictions.recv() => match evicted {
+						Ok(evicted) => evicted,
+						// The GUI keeps a few more logs than the server until the next eviction.
+						Err(RecvError::Lagged(_)) => continue,
+						Err(RecvError::Closed) => break,
+					},
+					// The GUI disconnected.
+					() = tx.closed() => break,
+				};
+
+				if tx.send(Ok(evicted)).await.is_err() {
+					break;
+				}
+			}
+
+			trace!("Stopped sending evicted logs to {}", remote_addr.unwrap());
+		});
+
+		Ok(Response::new(ReceiverStream::new(rx)))
+	}
+}
+
 // endregion
 // region: oauth implementation
 
@@ -602,6 +1477,30 @@ fn generate_github_login_url() -> String {
 	authorize_url.to_string()
 }
 
+// Whether loggers may send gzip compressed logs, read from the comma separated
+// `ACCEPTED_ENCODINGS` environment variable. Defaults to accepting gzip.
+fn accepts_gzip() -> bool {
+	let Ok(encodings) = env::var("ACCEPTED_ENCODINGS") else {
+		return true;
+	};
+
+	let mut accepts_gzip = false;
+
+	for encoding in encodings
+		.split(',')
+		.map(str::trim)
+		.filter(|encoding| !encoding.is_empty())
+	{
+		match encoding.to_lowercase().as_str() {
+			"gzip" => accepts_gzip = true,
+			"identity" => {},
+			encoding => warn!("Ignoring unsupported encoding in ACCEPTED_ENCODINGS: {encoding}"),
+		}
+	}
+
+	accepts_gzip
+}
+
 fn generate_token() -> String {
 	let mut rng = thread_rng();
 	let secret = Alphanumeric.sample_string(&mut rng, 50);
@@ -697,23 +1596,18 @@ pub async fn run_server(
 	let data_dir = data_dir.to_string_lossy().to_string();
 	let db_file = format!("{data_dir}/db.sqlite");
 
-	// If the DB file does not exist or is completely empty, then create and create
-	// the necessary table.
-	if !Path::new(&db_file).exists() || File::open(&db_file)?.metadata()?.len() == 0 {
+	// The migrations create the tables of a new DB file, and upgrade the tables of
+	// one from an older version.
+	if !Path::new(&db_file).exists() {
 		File::create(&db_file)?;
-
-		let db_connection = Database::connect(format!("sqlite:{db_file}")).await?;
-
-		let backend = db_connection.get_database_backend();
-		let schema = Schema::new(backend);
-		let statement = backend.build(&schema.create_table_from_entity(Entity));
-
-		info!("Creating initial SQLite database");
-
-		db_connection.execute(statement).await?;
+		info!("Creating SQLite database");
 	}
 
 	let db_connection = Database::connect(format!("sqlite:{db_file}")).await?;
+	Migrator::up(&db_connection, None).await?;
+
+	let saved_logs = log_store::load(&db_connection).await?;
+	let retention_policy = RetentionPolicy::from_env();
 
 	let host = if let Some(host) = host {
 		host
@@ -723,6 +1617,13 @@ pub async fn run_server(
 
 	let port = if let Some(port) = port { port } else { 3002 };
 
+	// The listener is bound up-front so that the actual port is known when
+	// given port 0.
+	let grpc_addr: SocketAddr = format!("{host}:{port}").parse()?;
+	let listener = TcpListener::bind(grpc_addr).await?;
+	let grpc_addr = listener.local_addr()?;
+	let port = u32::from(grpc_addr.port());
+
 	let logs = Arc::new(RwLock::new(VecDeque::new()));
 
 	let logs_service = Service {
@@ -730,30 +1631,85 @@ pub async fn run_server(
 		port,
 		uptime: Instant::now(),
 		logs: Arc::clone(&logs),
+		log_metadata: Arc::new(DashMap::new()),
+		received_log_ids: Arc::new(DashMap::new()),
+		test_results: Arc::new(DashMap::new()),
+		call_sites: Arc::new(DashMap::new()),
+		call_site_rules: Arc::new(DashMap::new()),
+		call_site_rule_updates: broadcast::channel(RULE_UPDATE_BUFFER_SIZE).0,
+		paused_breakpoints: Arc::new(DashMap::new()),
+		environments: Arc::new(DashMap::new()),
+		retention_policy,
+		retained_logs: Arc::new(DashMap::new()),
+		evicted_logs: broadcast::channel(EVICTION_BUFFER_SIZE).0,
 		connections: Arc::new(RwLock::new(DashMap::new())),
 		db_connection: Arc::new(db_connection),
 		requires_authentication,
 	};
 
+	info!("Loaded {} saved log(s)", saved_logs.len());
+
+	for saved in saved_logs {
+		let log = saved.log;
+
+		logs_service
+			.received_log_ids
+			.insert(log.uuid.clone(), Instant::now());
+		logs_service.retained_logs.insert(
+			log.uuid.clone(),
+			RetainedLog::new(
+				&log,
+				saved.metadata.as_ref(),
+				saved.received_at,
+				saved.pinned,
+			),
+		);
+
+		if let Some(metadata) = saved.metadata {
+			logs_service.store_metadata(&log.uuid, metadata);
+		}
+
+		logs.write().await.push_back(log);
+	}
+
 	logs_service.start_backup_thread();
+	logs_service.start_retention_thread();
 
 	let server_service = LogServerService::new(logs_service.clone());
-	let client_service = LogClientService::new(logs_service.clone());
+	let mut client_service = LogClientService::new(logs_service.clone());
+	let extended_server_service = ExtendedLogServerService::new(logs_service.clone());
+	let mut extended_client_service = ExtendedLogClientService::new(logs_service.clone());
+
+	// Compression is handled by the gRPC services themselves, so this also
+	// applies to requests made through tonic-web.
+	if accepts_gzip() {
+		client_service = client_service.accept_gzip();
+		extended_client_service = extended_client_service.accept_gzip();
+	}
 	let auth_service = AuthenticationServer::new(logs_service);
 
-	let grpc_addr = format!("{host}:{port}").parse()?;
-
 	info!("Starting gPRC server on {grpc_addr}...");
 
+	let discovery_guard =
+		DiscoveryGuard::write(Path::new(&data_dir), grpc_addr, requires_authentication)
+			.map_err(|error| warn!("Could not write discovery file: {error}"))
+			.ok();
+
 	let (error_sender, error_receiver) = mpsc::unbounded_channel();
 
 	let handle = tokio::spawn(async move {
+		// Dropped, removing the discovery file, once the server stops or the
+		// runtime shuts down.
+		let _discovery_guard = discovery_guard;
+
 		Server::builder()
 			.accept_http1(true)
 			.add_service(tonic_web::enable(server_service))
 			.add_service(tonic_web::enable(client_service))
+			.add_service(tonic_web::enable(extended_server_service))
+			.add_service(tonic_web::enable(extended_client_service))
 			.add_service(tonic_web::enable(auth_service))
-			.serve(grpc_addr)
+			.serve_with_incoming(TcpListenerStream::new(listener))
 			.await
 			.map_err(|error| error_sender.send(error.into()))
 	});
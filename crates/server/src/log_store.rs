@@ -0,0 +1,360 @@
+//! Saves received logs, along with their stack and code snippet, in the
+//! server's database so that they are still there after it restarts.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use codectrl_protobuf_bindings::{
+	data::{BacktraceData, Log},
+	extensions::LogMetadata,
+};
+use sea_orm::{
+	sea_query::Expr, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DatabaseConnection,
+	DatabaseTransaction, DbErr, EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+
+use crate::entity::{backtrace_data, code_snippet, log};
+
+/// A log loaded from the database.
+pub struct SavedLog {
+	pub log: Log,
+	pub metadata: Option<LogMetadata>,
+	pub received_at: SystemTime,
+	pub pinned: bool,
+}
+
+/// Saves `log` and its `metadata`, received at `received_at`. The log is saved in a
+/// single transaction, so it is never loaded without its stack or code snippet.
+pub async fn save(
+	db: &DatabaseConnection,
+	log: &Log,
+	metadata: Option<&LogMetadata>,
+	received_at: SystemTime,
+) -> Result<(), DbErr> {
+	let transaction = db.begin().await?;
+
+	insert(&transaction, log, metadata, received_at).await?;
+
+	transaction.commit().await
+}
+
+/// Saves every log in `logs` along with its metadata, all received at
+/// `received_at`, in a single transaction.
+pub async fn save_all(
+	db: &DatabaseConnection,
+	logs: &[(Log, Option<LogMetadata>)],
+	received_at: SystemTime,
+) -> Result<(), DbErr> {
+	let transaction = db.begin().await?;
+
+	for (log, metadata) in logs {
+		insert(&transaction, log, metadata.as_ref(), received_at).await?;
+	}
+
+	transaction.commit().await
+}
+
+async fn insert(
+	transaction: &DatabaseTransaction,
+	log: &Log,
+	metadata: Option<&LogMetadata>,
+	received_at: SystemTime,
+) -> Result<(), DbErr> {
+	let model = log::ActiveModel {
+		id: NotSet,
+		uuid: Set(log.uuid.clone()),
+		line_number: Set(log.line_number),
+		file_name: Set(log.file_name.clone()),
+		message: Set(log.message.clone()),
+		message_type: Set(log.message_type.clone()),
+		address: Set(log.address.clone()),
+		warnings: Set(to_json(&log.warnings)?),
+		language: Set(log.language.clone()),
+		metadata: Set(metadata.map(to_json).transpose()?),
+		received_at: Set(to_millis(received_at)),
+		pinned: Set(false),
+	}
+	.insert(transaction)
+	.await?;
+
+	let stack = log
+		.stack
+		.iter()
+		.zip(0..)
+		.map(|(frame, position)| backtrace_data::ActiveModel {
+			id: NotSet,
+			log_id: Set(model.id),
+			position: Set(position),
+			name: Set(frame.name.clone()),
+			file_path: Set(frame.file_path.clone()),
+			line_number: Set(frame.line_number),
+			column_number: Set(frame.column_number),
+			code: Set(frame.code.clone()),
+		})
+		.collect::<Vec<_>>();
+
+	// Inserting no rows is an error.
+	if !stack.is_empty() {
+		backtrace_data::Entity::insert_many(stack)
+			.exec(transaction)
+			.await?;
+	}
+
+	let snippet = log
+		.code_snippet
+		.iter()
+		.map(|(line_number, code)| code_snippet::ActiveModel {
+			id: NotSet,
+			log_id: Set(model.id),
+			line_number: Set(*line_number),
+			code: Set(code.clone()),
+		})
+		.collect::<Vec<_>>();
+
+	if !snippet.is_empty() {
+		code_snippet::Entity::insert_many(snippet)
+			.exec(transaction)
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Loads every saved log, in the order that they were received.
+pub async fn load(db: &DatabaseConnection) -> Result<Vec<SavedLog>, DbErr> {
+	let mut stacks: HashMap<i64, Vec<BacktraceData>> = HashMap::new();
+	let mut snippets: HashMap<i64, BTreeMap<u32, String>> = HashMap::new();
+
+	for frame in backtrace_data::Entity::find()
+		.order_by_asc(backtrace_data::Column::Position)
+		.all(db)
+		.await?
+	{
+		stacks.entry(frame.log_id).or_default().push(BacktraceData {
+			name: frame.name,
+			file_path: frame.file_path,
+			line_number: frame.line_number,
+			column_number: frame.column_number,
+			code: frame.code,
+		});
+	}
+
+	for line in code_snippet::Entity::find().all(db).await? {
+		snippets
+			.entry(line.log_id)
+			.or_default()
+			.insert(line.line_number, line.code);
+	}
+
+	let logs = log::Entity::find()
+		.order_by_asc(log::Column::Id)
+		.all(db)
+		.await?
+		.into_iter()
+		.map(|model| {
+			// Metadata that can't be read, such as from a newer version, is dropped
+			// rather than the whole log.
+			let metadata = model
+				.metadata
+				.as_deref()
+				.and_then(|metadata| serde_json::from_str(metadata).ok());
+
+			let log = Log {
+				uuid: model.uuid,
+				stack: stacks.remove(&model.id).unwrap_or_default(),
+				line_number: model.line_number,
+				file_name: model.file_name,
+				code_snippet: snippets.remove(&model.id).unwrap_or_default(),
+				message: model.message,
+				message_type: model.message_type,
+				address: model.address,
+				warnings: serde_json::from_str(&model.warnings).unwrap_or_default(),
+				language: model.language,
+			};
+
+			SavedLog {
+				log,
+				metadata,
+				received_at: UNIX_EPOCH
+					+ Duration::from_millis(u64::try_from(model.received_at).unwrap_or_default()),
+				pinned: model.pinned,
+			}
+		})
+		.collect();
+
+	Ok(logs)
+}
+
+/// Deletes the logs with the IDs `uuids`, along with their stacks and code
+/// snippets.
+pub async fn delete(db: &DatabaseConnection, uuids: Vec<String>) -> Result<(), DbErr> {
+	log::Entity::delete_many()
+		.filter(log::Column::Uuid.is_in(uuids))
+		.exec(db)
+		.await?;
+
+	Ok(())
+}
+
+pub async fn set_pinned(db: &DatabaseConnection, uuid: &str, pinned: bool) -> Result<(), DbErr> {
+	log::Entity::update_many()
+		.col_expr(log::Column::Pinned, Expr::value(pinned))
+		.filter(log::Column::Uuid.eq(uuid))
+		.exec(db)
+		.await?;
+
+	Ok(())
+}
+
+// Times are saved as milliseconds since the Unix epoch.
+fn to_millis(time: SystemTime) -> i64 {
+	time.duration_since(UNIX_EPOCH).map_or(0, |time| {
+		i64::try_from(time.as_millis()).unwrap_or(i64::MAX)
+	})
+}
+
+fn to_json(value: &impl serde::Serialize) -> Result<String, DbErr> {
+	serde_json::to_string(value).map_err(|error| DbErr::Custom(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, UNIX_EPOCH};
+
+	use codectrl_protobuf_bindings::{
+		data::{BacktraceData, Log},
+		extensions::LogMetadata,
+	};
+	use sea_orm::{Database, DatabaseConnection, EntityTrait};
+	use sea_orm_migration::MigratorTrait;
+
+	use super::{delete, load, save, save_all, set_pinned};
+	use crate::{
+		entity::{backtrace_data, code_snippet},
+		migration::Migrator,
+	};
+
+	async fn connect() -> DatabaseConnection {
+		let db = Database::connect("sqlite::memory:").await.unwrap();
+		Migrator::up(&db, None).await.unwrap();
+
+		db
+	}
+
+	fn frame(name: &str, line_number: u32) -> BacktraceData {
+		BacktraceData {
+			name: name.to_string(),
+			file_path: String::from("src/main.rs"),
+			line_number,
+			column_number: 5,
+			code: format!("{name}();"),
+		}
+	}
+
+	fn log(uuid: &str) -> Log {
+		Log {
+			uuid: uuid.to_string(),
+			stack: vec![frame("main", 20), frame("run", 12), frame("inner", 4)],
+			line_number: 4,
+			file_name: String::from("src/main.rs"),
+			code_snippet: [
+				(3, String::from("fn inner() {")),
+				(4, String::from("\tlog!(\"Hello\");")),
+				(5, String::from("}")),
+			]
+			.into(),
+			message: String::from("\"Hello\""),
+			message_type: String::from("&str"),
+			address: String::from("127.0.0.1:52123"),
+			warnings: vec![String::from("A warning")],
+			language: String::from("Rust"),
+		}
+	}
+
+	#[tokio::test]
+	async fn round_trip() {
+		let db = connect().await;
+		let metadata = LogMetadata {
+			file_hashes: [(String::from("src/main.rs"), String::from("abc123"))].into(),
+			snippet_hash: String::from("def456"),
+			build_time: Some(1_000),
+			session_id: String::from("session"),
+			..LogMetadata::default()
+		};
+		let received_at = UNIX_EPOCH + Duration::from_millis(1_500);
+
+		save(&db, &log("first"), Some(&metadata), received_at)
+			.await
+			.unwrap();
+		save(&db, &log("second"), None, received_at).await.unwrap();
+
+		let saved = load(&db).await.unwrap();
+
+		assert_eq!(saved.len(), 2);
+		assert_eq!(saved[0].log, log("first"));
+		assert_eq!(saved[0].metadata.as_ref(), Some(&metadata));
+		assert_eq!(saved[0].received_at, received_at);
+		assert!(!saved[0].pinned);
+		assert_eq!(saved[1].log, log("second"));
+		assert!(saved[1].metadata.is_none());
+	}
+
+	#[tokio::test]
+	async fn round_trip_batch() {
+		let db = connect().await;
+		let metadata = LogMetadata {
+			session_id: String::from("session"),
+			..LogMetadata::default()
+		};
+		let logs = [
+			(log("first"), Some(metadata.clone())),
+			(log("second"), None),
+		];
+
+		save_all(&db, &logs, UNIX_EPOCH).await.unwrap();
+
+		let saved = load(&db).await.unwrap();
+
+		assert_eq!(saved.len(), 2);
+		assert_eq!(saved[0].log, log("first"));
+		assert_eq!(saved[0].metadata.as_ref(), Some(&metadata));
+		assert_eq!(saved[1].log, log("second"));
+		assert_eq!(saved[1].log.stack.len(), 3);
+	}
+
+	#[tokio::test]
+	async fn pinned() {
+		let db = connect().await;
+
+		save(&db, &log("pinned"), None, UNIX_EPOCH).await.unwrap();
+		set_pinned(&db, "pinned", true).await.unwrap();
+
+		assert!(load(&db).await.unwrap()[0].pinned);
+	}
+
+	#[tokio::test]
+	async fn delete_cascades() {
+		let db = connect().await;
+
+		save(&db, &log("deleted"), None, UNIX_EPOCH).await.unwrap();
+		save(&db, &log("kept"), None, UNIX_EPOCH).await.unwrap();
+		delete(&db, vec![String::from("deleted")]).await.unwrap();
+
+		let saved = load(&db).await.unwrap();
+
+		assert_eq!(saved.len(), 1);
+		assert_eq!(saved[0].log.uuid, "kept");
+
+		// Only the stack and code snippet of the kept log are left.
+		assert_eq!(
+			backtrace_data::Entity::find().all(&db).await.unwrap().len(),
+			3
+		);
+		assert_eq!(
+			code_snippet::Entity::find().all(&db).await.unwrap().len(),
+			3
+		);
+	}
+}
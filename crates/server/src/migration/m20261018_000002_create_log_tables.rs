@@ -0,0 +1,163 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// Databases from before migrations were added may already have these
+		// tables.
+		manager
+			.create_table(
+				Table::create()
+					.table(Log::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(Log::Id)
+							.integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(Log::Uuid).string().not_null().unique_key())
+					.col(ColumnDef::new(Log::LineNumber).unsigned().not_null())
+					.col(ColumnDef::new(Log::FileName).string().not_null())
+					.col(ColumnDef::new(Log::Message).string().not_null())
+					.col(ColumnDef::new(Log::MessageType).string().not_null())
+					.col(ColumnDef::new(Log::Address).string().not_null())
+					.col(ColumnDef::new(Log::Warnings).string().not_null())
+					.col(ColumnDef::new(Log::Language).string().not_null())
+					.col(ColumnDef::new(Log::Metadata).string())
+					.to_owned(),
+			)
+			.await?;
+
+		manager
+			.create_table(
+				Table::create()
+					.table(BacktraceData::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(BacktraceData::Id)
+							.integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(BacktraceData::LogId).integer().not_null())
+					.col(
+						ColumnDef::new(BacktraceData::Position)
+							.unsigned()
+							.not_null(),
+					)
+					.col(ColumnDef::new(BacktraceData::Name).string().not_null())
+					.col(ColumnDef::new(BacktraceData::FilePath).string().not_null())
+					.col(
+						ColumnDef::new(BacktraceData::LineNumber)
+							.unsigned()
+							.not_null(),
+					)
+					.col(
+						ColumnDef::new(BacktraceData::ColumnNumber)
+							.unsigned()
+							.not_null(),
+					)
+					.col(ColumnDef::new(BacktraceData::Code).string().not_null())
+					.foreign_key(&mut log_foreign_key(
+						BacktraceData::Table,
+						BacktraceData::LogId,
+					))
+					.to_owned(),
+			)
+			.await?;
+
+		manager
+			.create_table(
+				Table::create()
+					.table(CodeSnippet::Table)
+					.if_not_exists()
+					.col(
+						ColumnDef::new(CodeSnippet::Id)
+							.integer()
+							.not_null()
+							.auto_increment()
+							.primary_key(),
+					)
+					.col(ColumnDef::new(CodeSnippet::LogId).integer().not_null())
+					.col(
+						ColumnDef::new(CodeSnippet::LineNumber)
+							.unsigned()
+							.not_null(),
+					)
+					.col(ColumnDef::new(CodeSnippet::Code).string().not_null())
+					.foreign_key(&mut log_foreign_key(CodeSnippet::Table, CodeSnippet::LogId))
+					.to_owned(),
+			)
+			.await
+	}
+
+	async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		for table in [
+			CodeSnippet::Table.into_iden(),
+			BacktraceData::Table.into_iden(),
+			Log::Table.into_iden(),
+		] {
+			manager
+				.drop_table(Table::drop().table(table).to_owned())
+				.await?;
+		}
+
+		Ok(())
+	}
+}
+
+// Deletes the rows of `table` along with the log that they belong to.
+fn log_foreign_key(
+	table: impl Iden + 'static,
+	column: impl Iden + 'static,
+) -> ForeignKeyCreateStatement {
+	ForeignKey::create()
+		.from(table, column)
+		.to(Log::Table, Log::Id)
+		.on_update(ForeignKeyAction::Cascade)
+		.on_delete(ForeignKeyAction::Cascade)
+		.to_owned()
+}
+
+#[derive(Iden)]
+enum Log {
+	Table,
+	Id,
+	Uuid,
+	LineNumber,
+	FileName,
+	Message,
+	MessageType,
+	Address,
+	Warnings,
+	Language,
+	Metadata,
+}
+
+#[derive(Iden)]
+enum BacktraceData {
+	Table,
+	Id,
+	LogId,
+	Position,
+	Name,
+	FilePath,
+	LineNumber,
+	ColumnNumber,
+	Code,
+}
+
+#[derive(Iden)]
+enum CodeSnippet {
+	Table,
+	Id,
+	LogId,
+	LineNumber,
+	Code,
+}
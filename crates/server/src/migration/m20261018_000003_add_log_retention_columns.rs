@@ -0,0 +1,78 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+	async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		// SQLite can only add one column at a time.
+		manager
+			.alter_table(
+				Table::alter()
+					.table(Log::Table)
+					.add_column(
+						ColumnDef::new(Log::ReceivedAt)
+							.big_integer()
+							.not_null()
+							.default(0),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		manager
+			.alter_table(
+				Table::alter()
+					.table(Log::Table)
+					.add_column(
+						ColumnDef::new(Log::Pinned)
+							.boolean()
+							.not_null()
+							.default(false),
+					)
+					.to_owned(),
+			)
+			.await?;
+
+		// Logs saved before now are treated as received now, rather than being
+		// evicted straight away by a maximum age.
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0_i64, |now| now.as_millis().try_into().unwrap_or(i64::MAX));
+
+		manager
+			.exec_stmt(
+				Query::update()
+					.table(Log::Table)
+					.value(Log::ReceivedAt, now)
+					.to_owned(),
+			)
+			.await
+	}
+
+	async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+		for column in [Log::ReceivedAt, Log::Pinned] {
+			manager
+				.alter_table(
+					Table::alter()
+						.table(Log::Table)
+						.drop_column(column)
+						.to_owned(),
+				)
+				.await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Iden)]
+enum Log {
+	Table,
+	// Milliseconds since the Unix epoch.
+	ReceivedAt,
+	Pinned,
+}
@@ -0,0 +1,129 @@
+//! Migrations of the server's database, which are run by `run_server` on
+//! startup. Each migration is applied once, in order, and recorded in the
+//! `seaql_migrations` table, so databases created by older versions of the
+//! server are upgraded in place.
+//!
+//! New migrations are added to the end of [`Migrator::migrations`], and are
+//! never changed once released.
+
+use sea_orm_migration::prelude::*;
+
+mod m20261018_000001_create_connection_table;
+mod m20261018_000002_create_log_tables;
+mod m20261018_000003_add_log_retention_columns;
+
+pub struct Migrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for Migrator {
+	fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+		vec![
+			Box::new(m20261018_000001_create_connection_table::Migration),
+			Box::new(m20261018_000002_create_log_tables::Migration),
+			Box::new(m20261018_000003_add_log_retention_columns::Migration),
+		]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, UNIX_EPOCH};
+
+	use codectrl_protobuf_bindings::data::Log;
+	use sea_orm::{
+		ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, Schema, Set,
+	};
+	use sea_orm_migration::MigratorTrait;
+
+	use super::Migrator;
+	use crate::{entity::connection, log_store};
+
+	// sea-orm only opens a single connection to SQLite by default, so the
+	// in-memory database lasts for as long as the connection does.
+	async fn connect() -> DatabaseConnection { Database::connect("sqlite::memory:").await.unwrap() }
+
+	async fn applied_migrations(db: &DatabaseConnection) -> Vec<String> {
+		Migrator::get_migration_models(db)
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|migration| migration.version)
+			.collect()
+	}
+
+	fn all_migrations() -> Vec<String> {
+		Migrator::migrations()
+			.iter()
+			.map(|migration| migration.name().to_string())
+			.collect()
+	}
+
+	#[tokio::test]
+	async fn empty_database() {
+		let db = connect().await;
+
+		Migrator::up(&db, None).await.unwrap();
+
+		assert_eq!(applied_migrations(&db).await, all_migrations());
+		assert!(log_store::load(&db).await.unwrap().is_empty());
+	}
+
+	// Databases from before migrations were added only have the `connection`
+	// table, created from its entity, and no `seaql_migrations` table.
+	#[tokio::test]
+	async fn baseline_database() {
+		let db = connect().await;
+		let backend = db.get_database_backend();
+
+		db.execute(backend.build(&Schema::new(backend).create_table_from_entity(connection::Entity)))
+			.await
+			.unwrap();
+
+		connection::ActiveModel {
+			uuid: Set(String::from("existing-connection")),
+			sent_logs: Set(None),
+		}
+		.insert(&db)
+		.await
+		.unwrap();
+
+		Migrator::up(&db, None).await.unwrap();
+
+		assert_eq!(applied_migrations(&db).await, all_migrations());
+		assert_eq!(
+			connection::Entity::find().all(&db).await.unwrap(),
+			[connection::Model {
+				uuid: String::from("existing-connection"),
+				sent_logs: None,
+			}]
+		);
+		assert!(log_store::load(&db).await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn running_twice_is_a_no_op() {
+		let db = connect().await;
+
+		Migrator::up(&db, None).await.unwrap();
+
+		let log = Log {
+			uuid: String::from("saved-log"),
+			..Log::default()
+		};
+		let received_at = UNIX_EPOCH + Duration::from_secs(1_000);
+
+		log_store::save(&db, &log, None, received_at).await.unwrap();
+
+		Migrator::up(&db, None).await.unwrap();
+
+		assert_eq!(applied_migrations(&db).await, all_migrations());
+
+		// Running the retention migration again would have reset the time the log
+		// was received at.
+		let saved = log_store::load(&db).await.unwrap();
+
+		assert_eq!(saved.len(), 1);
+		assert_eq!(saved[0].log.uuid, "saved-log");
+		assert_eq!(saved[0].received_at, received_at);
+	}
+}
@@ -0,0 +1,381 @@
+//! Limits on how many logs the server keeps, so that a server that is left
+//! running doesn't grow without bound. Most limits apply to each session
+//! separately, so a chatty program doesn't evict the logs of a quiet one. As
+//! every run of a program is a new session, the total limits then cap the logs
+//! of every session combined. Pinned logs are exempt from all of them.
+
+use std::{
+	collections::{HashMap, HashSet},
+	env,
+	net::SocketAddr,
+	str::FromStr,
+	time::{Duration, SystemTime},
+};
+
+use codectrl_protobuf_bindings::{data::Log, extensions::LogMetadata};
+use log::warn;
+
+/// The limits on the logs kept for each session, and for every session
+/// combined. Logs over a limit are evicted oldest first, both from memory and
+/// from the database. `None` is no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+	/// The most logs that are kept for each session.
+	pub count: Option<usize>,
+	/// How long logs are kept for after they're received.
+	pub age: Option<Duration>,
+	/// The most bytes of logs that are kept for each session, counted as JSON.
+	pub bytes: Option<usize>,
+	/// The most logs that are kept for every session combined.
+	pub total_count: Option<usize>,
+	/// The most bytes of logs that are kept for every session combined, counted
+	/// as JSON.
+	pub total_bytes: Option<usize>,
+}
+
+impl Default for RetentionPolicy {
+	fn default() -> Self {
+		Self {
+			count: Some(10_000),
+			age: None,
+			bytes: Some(256 * 1024 * 1024),
+			total_count: Some(100_000),
+			total_bytes: Some(1024 * 1024 * 1024),
+		}
+	}
+}
+
+impl RetentionPolicy {
+	/// Reads the limits from the `MAX_LOGS`, `MAX_LOG_AGE` (in seconds),
+	/// `MAX_LOG_BYTES`, `MAX_TOTAL_LOGS` and `MAX_TOTAL_LOG_BYTES` environment
+	/// variables, where 0 is no limit. Limits that aren't set keep their
+	/// default.
+	pub fn from_env() -> Self {
+		let default = Self::default();
+
+		Self {
+			count: limit("MAX_LOGS", default.count),
+			age: limit("MAX_LOG_AGE", default.age.map(|age| age.as_secs())).map(Duration::from_secs),
+			bytes: limit("MAX_LOG_BYTES", default.bytes),
+			total_count: limit("MAX_TOTAL_LOGS", default.total_count),
+			total_bytes: limit("MAX_TOTAL_LOG_BYTES", default.total_bytes),
+		}
+	}
+}
+
+// Reads a limit from the environment variable `name`, where 0 is no limit.
+// Returns `default` if the variable isn't set or is invalid.
+fn limit<T: FromStr + Default + PartialEq>(name: &str, default: Option<T>) -> Option<T> {
+	let Ok(value) = env::var(name) else {
+		return default;
+	};
+
+	let Ok(value) = value.parse::<T>() else {
+		warn!("Ignoring {name}, which should be a whole number but is \"{value}\"");
+		return default;
+	};
+
+	(value != T::default()).then_some(value)
+}
+
+/// What the retention limits need to know about a log.
+#[derive(Debug, Clone)]
+pub struct RetainedLog {
+	/// The session ID of the process that sent the log, or the host it was sent
+	/// from if it didn't send one.
+	pub session: String,
+	pub received_at: SystemTime,
+	pub size: usize,
+	pub pinned: bool,
+}
+
+impl RetainedLog {
+	pub fn new(
+		log: &Log,
+		metadata: Option<&LogMetadata>,
+		received_at: SystemTime,
+		pinned: bool,
+	) -> Self {
+		// The address ends with the port of the connection, which changes with
+		// almost every connection, so only the host identifies the sender.
+		let session = metadata
+			.map(|metadata| metadata.session_id.clone())
+			.filter(|session_id| !session_id.is_empty())
+			.unwrap_or_else(|| host(&log.address).to_string());
+
+		let size = serde_json::to_vec(log).map_or(0, |json| json.len())
+			+ metadata
+				.and_then(|metadata| serde_json::to_vec(metadata).ok())
+				.map_or(0, |json| json.len());
+
+		Self {
+			session,
+			received_at,
+			size,
+			pinned,
+		}
+	}
+}
+
+// Strips the port from `address`, which is either a socket address or a host
+// that may be followed by a port.
+fn host(address: &str) -> &str {
+	if let Ok(socket_address) = address.parse::<SocketAddr>() {
+		let port = socket_address.port().to_string();
+
+		return address
+			.strip_suffix(&port)
+			.and_then(|address| address.strip_suffix(':'))
+			.map_or(address, |host| {
+				host.trim_start_matches('[').trim_end_matches(']')
+			});
+	}
+
+	match address.rsplit_once(':') {
+		Some((host, port)) if !host.contains(':') && port.parse::<u16>().is_ok() => host,
+		_ => address,
+	}
+}
+
+/// Returns the IDs of the logs that are over the limits of `policy` at `now`.
+/// `logs` must be in the order that they were received.
+pub fn evictions(
+	policy: &RetentionPolicy,
+	logs: &[(String, RetainedLog)],
+	now: SystemTime,
+) -> HashSet<String> {
+	let mut sessions: HashMap<&str, Vec<&(String, RetainedLog)>> = HashMap::new();
+
+	for log in logs.iter().filter(|(_, log)| !log.pinned) {
+		sessions.entry(&log.1.session).or_default().push(log);
+	}
+
+	let mut evicted = HashSet::new();
+
+	for logs in sessions.into_values() {
+		let mut count = logs.len();
+		let mut bytes = logs.iter().map(|(_, log)| log.size).sum::<usize>();
+
+		// Oldest first, so every log after the first one within the limits is
+		// within them too.
+		for (uuid, log) in logs {
+			let expired = policy.age.is_some_and(|max_age| {
+				now
+					.duration_since(log.received_at)
+					.is_ok_and(|age| age > max_age)
+			});
+			let over_limit = policy.count.is_some_and(|max_count| count > max_count)
+				|| policy.bytes.is_some_and(|max_bytes| bytes > max_bytes);
+
+			if !expired && !over_limit {
+				break;
+			}
+
+			evicted.insert(uuid.clone());
+			count -= 1;
+			bytes -= log.size;
+		}
+	}
+
+	let remaining = logs
+		.iter()
+		.filter(|(uuid, log)| !log.pinned && !evicted.contains(uuid))
+		.collect::<Vec<_>>();
+	let mut count = remaining.len();
+	let mut bytes = remaining.iter().map(|(_, log)| log.size).sum::<usize>();
+
+	for (uuid, log) in remaining {
+		let over_limit = policy
+			.total_count
+			.is_some_and(|max_count| count > max_count)
+			|| policy
+				.total_bytes
+				.is_some_and(|max_bytes| bytes > max_bytes);
+
+		if !over_limit {
+			break;
+		}
+
+		evicted.insert(uuid.clone());
+		count -= 1;
+		bytes -= log.size;
+	}
+
+	evicted
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::{Duration, SystemTime};
+
+	use super::{evictions, host, RetainedLog, RetentionPolicy};
+
+	const NO_LIMITS: RetentionPolicy = RetentionPolicy {
+		count: None,
+		age: None,
+		bytes: None,
+		total_count: None,
+		total_bytes: None,
+	};
+
+	fn now() -> SystemTime { SystemTime::UNIX_EPOCH + Duration::from_secs(1_000) }
+
+	// A log from `session` received `age` seconds before `now()`.
+	fn log(uuid: &str, session: &str, age: u64, size: usize) -> (String, RetainedLog) {
+		(
+			uuid.to_string(),
+			RetainedLog {
+				session: session.to_string(),
+				received_at: now() - Duration::from_secs(age),
+				size,
+				pinned: false,
+			},
+		)
+	}
+
+	fn pinned(mut log: (String, RetainedLog)) -> (String, RetainedLog) {
+		log.1.pinned = true;
+		log
+	}
+
+	fn evicted(policy: RetentionPolicy, logs: &[(String, RetainedLog)]) -> Vec<String> {
+		let mut evicted = evictions(&policy, logs, now())
+			.into_iter()
+			.collect::<Vec<_>>();
+		evicted.sort();
+		evicted
+	}
+
+	#[test]
+	fn no_limits() {
+		let logs = [log("a", "one", 30, 10), log("b", "one", 20, 10)];
+
+		assert!(evicted(NO_LIMITS, &logs).is_empty());
+	}
+
+	#[test]
+	fn count_evicts_oldest_first() {
+		let policy = RetentionPolicy {
+			count: Some(2),
+			..NO_LIMITS
+		};
+		let logs = [
+			log("a", "one", 40, 10),
+			log("b", "one", 30, 10),
+			log("c", "one", 20, 10),
+			log("d", "one", 10, 10),
+		];
+
+		assert_eq!(evicted(policy, &logs), ["a", "b"]);
+	}
+
+	#[test]
+	fn age() {
+		let policy = RetentionPolicy {
+			age: Some(Duration::from_secs(25)),
+			..NO_LIMITS
+		};
+		let logs = [
+			log("a", "one", 40, 10),
+			log("b", "one", 30, 10),
+			log("c", "one", 20, 10),
+		];
+
+		assert_eq!(evicted(policy, &logs), ["a", "b"]);
+	}
+
+	#[test]
+	fn bytes() {
+		let policy = RetentionPolicy {
+			bytes: Some(25),
+			..NO_LIMITS
+		};
+		let logs = [
+			log("a", "one", 30, 10),
+			log("b", "one", 20, 10),
+			log("c", "one", 10, 10),
+		];
+
+		assert_eq!(evicted(policy, &logs), ["a"]);
+	}
+
+	#[test]
+	fn limits_apply_to_each_session() {
+		let policy = RetentionPolicy {
+			count: Some(1),
+			..NO_LIMITS
+		};
+		let logs = [
+			log("a", "one", 40, 10),
+			log("b", "two", 30, 10),
+			log("c", "one", 20, 10),
+			log("d", "three", 10, 10),
+		];
+
+		assert_eq!(evicted(policy, &logs), ["a"]);
+	}
+
+	#[test]
+	fn total_limits_apply_to_every_session() {
+		let logs = [
+			log("a", "one", 40, 10),
+			log("b", "two", 30, 10),
+			log("c", "three", 20, 10),
+			log("d", "four", 10, 10),
+		];
+
+		let policy = RetentionPolicy {
+			total_count: Some(3),
+			..NO_LIMITS
+		};
+		assert_eq!(evicted(policy, &logs), ["a"]);
+
+		let policy = RetentionPolicy {
+			total_bytes: Some(25),
+			..NO_LIMITS
+		};
+		assert_eq!(evicted(policy, &logs), ["a", "b"]);
+	}
+
+	#[test]
+	fn total_limits_apply_after_session_limits() {
+		let policy = RetentionPolicy {
+			count: Some(1),
+			total_count: Some(1),
+			..NO_LIMITS
+		};
+		let logs = [
+			log("a", "one", 40, 10),
+			log("b", "one", 30, 10),
+			log("c", "two", 20, 10),
+		];
+
+		assert_eq!(evicted(policy, &logs), ["a", "b"]);
+	}
+
+	#[test]
+	fn pinned_logs_are_never_evicted() {
+		let policy = RetentionPolicy {
+			count: Some(1),
+			age: Some(Duration::from_secs(5)),
+			total_count: Some(1),
+			..NO_LIMITS
+		};
+		let logs = [
+			pinned(log("a", "one", 40, 10)),
+			log("b", "one", 30, 10),
+			pinned(log("c", "two", 20, 10)),
+		];
+
+		assert_eq!(evicted(policy, &logs), ["b"]);
+	}
+
+	#[test]
+	fn session_falls_back_to_host() {
+		assert_eq!(host("127.0.0.1:52123"), "127.0.0.1");
+		assert_eq!(host("[::1]:52123"), "::1");
+		assert_eq!(host("example.com:52123"), "example.com");
+		assert_eq!(host("example.com"), "example.com");
+		assert_eq!(host("Unknown"), "Unknown");
+	}
+}
@@ -0,0 +1,274 @@
+#![cfg(test)]
+
+use std::{
+	collections::{HashSet, VecDeque},
+	sync::Arc,
+	time::{Duration, Instant},
+};
+
+use codectrl_protobuf_bindings::{
+	data::Log,
+	extensions::{
+		extended_log_server_client::ExtendedLogServerClient, BreakpointMetadata, ExtendedLog,
+		ExtendedLoggerClient, LogId, LogMetadata,
+	},
+	logs_service::{LoggerClient, RequestStatus},
+};
+use dashmap::DashMap;
+use sea_orm::Database;
+use sea_orm_migration::MigratorTrait;
+use tokio::{
+	net::TcpListener,
+	sync::{broadcast, RwLock},
+	time::timeout,
+};
+use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+use tonic::{
+	transport::{Channel, Server},
+	Code,
+};
+use uuid::Uuid;
+
+use crate::{
+	log_store, migration::Migrator, retention::RetentionPolicy, ExtendedLogClientService,
+	ExtendedLogServerService, LogClientService, Service, EVICTION_BUFFER_SIZE,
+	RULE_UPDATE_BUFFER_SIZE,
+};
+
+// Starts a server backed by an in-memory database on an ephemeral port, and
+// returns its service along with a channel connected to it.
+async fn start() -> (Service, Channel) {
+	let db_connection = Database::connect("sqlite::memory:").await.unwrap();
+	Migrator::up(&db_connection, None).await.unwrap();
+
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let address = listener.local_addr().unwrap();
+
+	let service = Service {
+		host: address.ip().to_string(),
+		port: u32::from(address.port()),
+		uptime: Instant::now(),
+		logs: Arc::new(RwLock::new(VecDeque::new())),
+		log_metadata: Arc::new(DashMap::new()),
+		received_log_ids: Arc::new(DashMap::new()),
+		test_results: Arc::new(DashMap::new()),
+		call_sites: Arc::new(DashMap::new()),
+		call_site_rules: Arc::new(DashMap::new()),
+		call_site_rule_updates: broadcast::channel(RULE_UPDATE_BUFFER_SIZE).0,
+		paused_breakpoints: Arc::new(DashMap::new()),
+		environments: Arc::new(DashMap::new()),
+		retention_policy: RetentionPolicy::default(),
+		retained_logs: Arc::new(DashMap::new()),
+		evicted_logs: broadcast::channel(EVICTION_BUFFER_SIZE).0,
+		connections: Arc::new(RwLock::new(DashMap::new())),
+		db_connection: Arc::new(db_connection),
+		requires_authentication: false,
+	};
+
+	tokio::spawn(
+		Server::builder()
+			.add_service(LogClientService::new(service.clone()))
+			.add_service(ExtendedLogClientService::new(service.clone()))
+			.add_service(ExtendedLogServerService::new(service.clone()))
+			.serve_with_incoming(TcpListenerStream::new(listener)),
+	);
+
+	let channel = Channel::from_shared(format!("http://{address}"))
+		.unwrap()
+		.connect()
+		.await
+		.unwrap();
+
+	(service, channel)
+}
+
+fn log(uuid: &str) -> Log {
+	Log {
+		uuid: uuid.to_string(),
+		message: String::from("\"Hello\""),
+		message_type: String::from("&str"),
+		file_name: String::from("src/main.rs"),
+		line_number: 4,
+		language: String::from("Rust"),
+		..Log::default()
+	}
+}
+
+fn breakpoint(uuid: &str) -> ExtendedLog {
+	ExtendedLog {
+		log: Some(log(uuid)),
+		metadata: Some(LogMetadata {
+			breakpoint: Some(BreakpointMetadata {
+				timeout_millis: 30_000,
+			}),
+			..LogMetadata::default()
+		}),
+	}
+}
+
+#[tokio::test]
+async fn resent_log_is_stored_once() {
+	let (service, channel) = start().await;
+	let mut logger = LoggerClient::new(channel.clone());
+	let mut extended_logger = ExtendedLoggerClient::new(channel);
+	let uuid = Uuid::new_v4().hyphenated().to_string();
+
+	logger.send_log(log(&uuid)).await.unwrap();
+	logger.send_log(log(&uuid)).await.unwrap();
+	extended_logger
+		.send_extended_log(ExtendedLog {
+			log: Some(log(&uuid)),
+			metadata: None,
+		})
+		.await
+		.unwrap();
+
+	assert_eq!(service.logs.read().await.len(), 1);
+	assert_eq!(log_store::load(&service.db_connection).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn resent_log_is_confirmed() {
+	let (_service, channel) = start().await;
+	let mut logger = LoggerClient::new(channel.clone());
+	let mut extended_logger = ExtendedLoggerClient::new(channel);
+	let uuid = Uuid::new_v4().hyphenated().to_string();
+
+	let first = logger.send_log(log(&uuid)).await.unwrap().into_inner();
+	let resent = logger.send_log(log(&uuid)).await.unwrap().into_inner();
+
+	assert_eq!(first.status, i32::from(RequestStatus::Confirmed));
+	assert_eq!(resent.status, i32::from(RequestStatus::Confirmed));
+
+	// A streamed log is acknowledged by its ID however many times it is sent.
+	let resent = ExtendedLog {
+		log: Some(log(&uuid)),
+		metadata: None,
+	};
+	let acks = extended_logger
+		.stream_extended_logs(tokio_stream::iter(vec![resent.clone(), resent]))
+		.await
+		.unwrap()
+		.into_inner()
+		.collect::<Vec<_>>()
+		.await;
+
+	assert_eq!(acks.len(), 2);
+
+	for ack in acks {
+		let ack = ack.unwrap();
+
+		assert_eq!(ack.uuid, uuid);
+		assert_eq!(ack.status, i32::from(RequestStatus::Confirmed));
+	}
+}
+
+#[tokio::test]
+async fn invalid_uuid_is_replaced() {
+	let (service, channel) = start().await;
+	let mut logger = LoggerClient::new(channel);
+	let invalid = ["", "not-a-uuid", &Uuid::nil().hyphenated().to_string()];
+
+	for uuid in invalid {
+		logger.send_log(log(uuid)).await.unwrap();
+	}
+
+	let logs = service.logs.read().await;
+	let uuids = logs
+		.iter()
+		.map(|log| Uuid::try_parse(&log.uuid).unwrap())
+		.collect::<HashSet<_>>();
+
+	// Each log is given a new ID of its own, rather than being deduplicated.
+	assert_eq!(logs.len(), invalid.len());
+	assert_eq!(uuids.len(), invalid.len());
+	assert!(!uuids.contains(&Uuid::nil()));
+}
+
+#[tokio::test]
+async fn resume_before_waiting() {
+	let (_service, channel) = start().await;
+	let mut logger = ExtendedLoggerClient::new(channel.clone());
+	let mut gui = ExtendedLogServerClient::new(channel);
+	let uuid = Uuid::new_v4().hyphenated().to_string();
+
+	logger.send_extended_log(breakpoint(&uuid)).await.unwrap();
+
+	// "Continue" is pressed before the program starts waiting.
+	gui
+		.resume_breakpoint(LogId { uuid: uuid.clone() })
+		.await
+		.unwrap();
+
+	let result = timeout(
+		Duration::from_secs(5),
+		logger.wait_for_resume(LogId { uuid: uuid.clone() }),
+	)
+	.await
+	.expect("The earlier resume was lost")
+	.unwrap()
+	.into_inner();
+
+	assert_eq!(result.status, i32::from(RequestStatus::Confirmed));
+
+	// The breakpoint is no longer paused once resumed.
+	let status = gui.resume_breakpoint(LogId { uuid }).await.unwrap_err();
+	assert_eq!(status.code(), Code::FailedPrecondition);
+}
+
+#[tokio::test]
+async fn resume_while_waiting() {
+	let (service, channel) = start().await;
+	let mut logger = ExtendedLoggerClient::new(channel.clone());
+	let mut gui = ExtendedLogServerClient::new(channel);
+	let uuid = Uuid::new_v4().hyphenated().to_string();
+
+	logger.send_extended_log(breakpoint(&uuid)).await.unwrap();
+
+	let waiting = tokio::spawn({
+		let uuid = uuid.clone();
+
+		async move { logger.wait_for_resume(LogId { uuid }).await }
+	});
+
+	// Waits for the program to start waiting, which is when its request holds
+	// the breakpoint's `Notify` alongside the map.
+	while service
+		.paused_breakpoints
+		.get(&uuid)
+		.map_or(0, |resumed| Arc::strong_count(resumed.value()))
+		< 2
+	{
+		tokio::task::yield_now().await;
+	}
+
+	gui.resume_breakpoint(LogId { uuid }).await.unwrap();
+
+	let result = timeout(Duration::from_secs(5), waiting)
+		.await
+		.expect("The breakpoint wasn't resumed")
+		.unwrap()
+		.unwrap()
+		.into_inner();
+
+	assert_eq!(result.status, i32::from(RequestStatus::Confirmed));
+}
+
+#[tokio::test]
+async fn resume_without_breakpoint() {
+	let (_service, channel) = start().await;
+	let mut logger = ExtendedLoggerClient::new(channel.clone());
+	let mut gui = ExtendedLogServerClient::new(channel);
+	let uuid = Uuid::new_v4().hyphenated().to_string();
+
+	logger
+		.send_extended_log(ExtendedLog {
+			log: Some(log(&uuid)),
+			metadata: Some(LogMetadata::default()),
+		})
+		.await
+		.unwrap();
+
+	let status = gui.resume_breakpoint(LogId { uuid }).await.unwrap_err();
+	assert_eq!(status.code(), Code::FailedPrecondition);
+}